@@ -13,8 +13,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     loop {
         let n = dev.read(&mut buf[..]).await?;
         println!("packet received {n} size");
-        for i in 0..n {
-            print!("{:x} ", buf[i]);
+        for b in &buf[..n] {
+            print!("{:x} ", b);
         }
         println!();
     }
@@ -1,7 +1,8 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 
 use crate::error::{Error, Result};
 
+#[allow(clippy::wrong_self_convention)]
 pub trait IntoIpv4Addr {
     fn into_ipv4(&self) -> Result<Ipv4Addr>;
 }
@@ -96,6 +97,114 @@ impl IntoIpv4Addr for SocketAddr {
     }
 }
 
+#[allow(clippy::wrong_self_convention)]
+pub trait IntoIpAddr {
+    fn into_ip(&self) -> Result<IpAddr>;
+
+    fn into_ipv6(&self) -> Result<Ipv6Addr> {
+        match self.into_ip()? {
+            IpAddr::V6(addr) => Ok(addr),
+            _ => Err(Error::InvalidAddress),
+        }
+    }
+}
+
+impl IntoIpAddr for str {
+    fn into_ip(&self) -> Result<IpAddr> {
+        self.parse().map_err(|_| Error::InvalidAddress)
+    }
+}
+
+impl IntoIpAddr for &str {
+    fn into_ip(&self) -> Result<IpAddr> {
+        (*self).into_ip()
+    }
+}
+
+impl IntoIpAddr for String {
+    fn into_ip(&self) -> Result<IpAddr> {
+        self.as_str().into_ip()
+    }
+}
+
+impl IntoIpAddr for &String {
+    fn into_ip(&self) -> Result<IpAddr> {
+        (**self).into_ip()
+    }
+}
+
+impl IntoIpAddr for IpAddr {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(*self)
+    }
+}
+
+impl IntoIpAddr for &IpAddr {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(**self)
+    }
+}
+
+impl IntoIpAddr for Ipv4Addr {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(IpAddr::V4(*self))
+    }
+}
+
+impl IntoIpAddr for &Ipv4Addr {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(IpAddr::V4(**self))
+    }
+}
+
+impl IntoIpAddr for Ipv6Addr {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(IpAddr::V6(*self))
+    }
+}
+
+impl IntoIpAddr for &Ipv6Addr {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(IpAddr::V6(**self))
+    }
+}
+
+impl IntoIpAddr for (u8, u8, u8, u8) {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(IpAddr::V4(Ipv4Addr::new(self.0, self.1, self.2, self.3)))
+    }
+}
+
+impl IntoIpAddr for [u16; 8] {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(IpAddr::V6(Ipv6Addr::from(*self)))
+    }
+}
+
+impl IntoIpAddr for SocketAddr {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(self.ip())
+    }
+}
+
+impl IntoIpAddr for &SocketAddr {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(self.ip())
+    }
+}
+
+impl IntoIpAddr for SocketAddrV4 {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(IpAddr::V4(*self.ip()))
+    }
+}
+
+impl IntoIpAddr for SocketAddrV6 {
+    fn into_ip(&self) -> Result<IpAddr> {
+        Ok(IpAddr::V6(*self.ip()))
+    }
+}
+
 pub trait Ipv4AddrExt {
     fn to_sockaddr(&self) -> libc::sockaddr;
     fn from_sockaddr(sock: libc::sockaddr) -> Self;
@@ -131,3 +240,21 @@ impl Ipv4AddrExt for Ipv4Addr {
         addr.sin_addr.s_addr.to_ne_bytes().into()
     }
 }
+
+pub trait Ipv6AddrExt {
+    fn to_in6_addr(&self) -> libc::in6_addr;
+    fn from_in6_addr(addr: libc::in6_addr) -> Self;
+}
+
+impl Ipv6AddrExt for Ipv6Addr {
+    fn to_in6_addr(&self) -> libc::in6_addr {
+        libc::in6_addr {
+            s6_addr: self.octets(),
+        }
+    }
+
+    fn from_in6_addr(addr: libc::in6_addr) -> Self {
+        // s6_addr is already in network byte order
+        addr.s6_addr.into()
+    }
+}
@@ -3,6 +3,10 @@ use bytes::{BufMut, Bytes, BytesMut};
 use std::io;
 use tokio_util::codec::{Decoder, Encoder};
 
+use crate::configuration::Layer;
+use crate::ethernet::{EthernetHeader, ETHERTYPE_IPV4, ETHERTYPE_IPV6};
+use crate::vnet::VirtioNetHdr;
+
 #[derive(Debug, Clone, Copy, Default)]
 pub enum PacketProtocol {
     #[default]
@@ -25,15 +29,23 @@ pub fn infer_proto(pkt: &[u8]) -> PacketProtocol {
 }
 
 impl PacketProtocol {
+    /// `Other(0)` for anything but IP, the EtherType is kept in the [`EthernetHeader`]
+    pub fn from_ether_type(ether_type: u16) -> Self {
+        match ether_type {
+            ETHERTYPE_IPV4 => PacketProtocol::Ipv4,
+            ETHERTYPE_IPV6 => PacketProtocol::Ipv6,
+            _ => PacketProtocol::Other(0),
+        }
+    }
+
     #[cfg(any(target_os = "macos", target_os = "ios"))]
     pub fn into_pi_field(self) -> Result<u16, io::Error> {
         match self {
             PacketProtocol::Ipv4 => Ok(libc::PF_INET as u16),
             PacketProtocol::Ipv6 => Ok(libc::PF_INET6 as u16),
-            PacketProtocol::Other(p) => Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("neither an Ipv4 nor Ipv6 packet: {p}"),
-            )),
+            PacketProtocol::Other(p) => Err(io::Error::other(format!(
+                "neither an Ipv4 nor Ipv6 packet: {p}"
+            ))),
         }
     }
 
@@ -42,21 +54,64 @@ impl PacketProtocol {
         match self {
             PacketProtocol::Ipv4 => Ok(libc::ETH_P_IP as u16),
             PacketProtocol::Ipv6 => Ok(libc::ETH_P_IPV6 as u16),
-            PacketProtocol::Other(p) => Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("neither an Ipv4 nor Ipv6 packet: {p}"),
-            )),
+            PacketProtocol::Other(p) => Err(io::Error::other(format!(
+                "neither an Ipv4 nor Ipv6 packet: {p}"
+            ))),
         }
     }
 }
 
-pub struct TunPacket(PacketProtocol, Bytes);
+pub struct TunPacket(
+    PacketProtocol,
+    Bytes,
+    Option<VirtioNetHdr>,
+    Option<EthernetHeader>,
+);
 
 impl TunPacket {
     pub fn new<T: Into<Bytes>>(pkt: T) -> Self {
         let pkt: Bytes = pkt.into();
         let proto = infer_proto(&pkt);
-        Self(proto, pkt)
+        Self(proto, pkt, None, None)
+    }
+
+    /// An Ethernet frame of a TAP device, the protocol is taken from its EtherType
+    pub fn from_frame<T: Into<Bytes>>(frame: T) -> Self {
+        let frame: Bytes = frame.into();
+        let header = EthernetHeader::parse(&frame);
+        let proto = header.as_ref().map_or(PacketProtocol::Other(0), |header| {
+            PacketProtocol::from_ether_type(header.ether_type)
+        });
+        Self(proto, frame, None, header)
+    }
+
+    /// Attach the virtio-net header written in front of the packet,
+    /// a zeroed one is used if the device expects one and none is set
+    pub fn with_vnet_hdr(mut self, hdr: VirtioNetHdr) -> Self {
+        self.2 = Some(hdr);
+        self
+    }
+
+    /// The virtio-net header the packet was read with
+    pub fn vnet_hdr(&self) -> Option<&VirtioNetHdr> {
+        self.2.as_ref()
+    }
+
+    /// The header of a frame read from or built for a TAP device
+    pub fn ethernet(&self) -> Option<&EthernetHeader> {
+        self.3.as_ref()
+    }
+
+    pub fn protocol(&self) -> PacketProtocol {
+        self.0
+    }
+
+    /// What follows the Ethernet header if any, the whole packet otherwise
+    pub fn payload(&self) -> &[u8] {
+        match self.3 {
+            Some(ref header) => &self.1[header.size()..],
+            None => &self.1,
+        }
     }
 
     pub fn get_bytes(&self) -> &[u8] {
@@ -75,11 +130,31 @@ impl From<TunPacket> for Bytes {
 }
 
 #[derive(Debug, Clone, Copy, Default)]
-pub struct TunPacketCodec(bool, i32);
+pub struct TunPacketCodec(bool, i32, usize, Layer);
 
 impl TunPacketCodec {
     pub fn new(pi: bool, mtu: i32) -> Self {
-        Self(pi, mtu)
+        Self(pi, mtu, 0, Layer::L3)
+    }
+
+    /// Frames of an L2 device start with an Ethernet header rather than an IP one
+    pub fn with_layer(mut self, layer: Layer) -> Self {
+        self.3 = layer;
+        self
+    }
+
+    /// Packets are prefixed with a virtio-net header of `len` bytes (after the packet information)
+    pub fn with_vnet_hdr(mut self, len: usize) -> Self {
+        self.2 = len;
+        self
+    }
+
+    /// The largest packet at the MTU, with a VLAN tagged Ethernet header on L2
+    fn max_frame(&self) -> usize {
+        match self.3 {
+            Layer::L2 => self.1 as usize + EthernetHeader::MIN_LEN + 4,
+            Layer::L3 => self.1 as usize,
+        }
     }
 }
 
@@ -104,15 +179,29 @@ impl Decoder for TunPacketCodec {
         // packet information
         if self.0 {
             // reserve enough space for next packet
-            buf.reserve(self.1 as usize + 4);
+            buf.reserve(self.max_frame() + 4 + self.2);
             // ignore the first 4 bytes
             let _ = pkt.split_to(4);
         } else {
-            buf.reserve(self.1 as usize);
+            buf.reserve(self.max_frame() + self.2);
         }
 
-        let proto = infer_proto(pkt.as_ref());
-        Ok(Some(TunPacket(proto, pkt.freeze())))
+        let vnet = if self.2 > 0 {
+            let hdr = VirtioNetHdr::parse(&pkt)
+                .filter(|_| pkt.len() >= self.2)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            let _ = pkt.split_to(self.2);
+            Some(hdr)
+        } else {
+            None
+        };
+
+        let mut pkt = match self.3 {
+            Layer::L2 => TunPacket::from_frame(pkt.freeze()),
+            Layer::L3 => TunPacket::new(pkt.freeze()),
+        };
+        pkt.2 = vnet;
+        Ok(Some(pkt))
     }
 }
 
@@ -120,23 +209,78 @@ impl Encoder<TunPacket> for TunPacketCodec {
     type Error = io::Error;
 
     fn encode(&mut self, item: TunPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        dst.reserve(item.get_bytes().len() + 4);
+        dst.reserve(item.get_bytes().len() + 4 + self.2);
+
+        let TunPacket(proto, pkt, vnet, ethernet) = item;
+        if self.0 {
+            let proto = match self.3 {
+                // the kernel reads the EtherType from the frame itself, this mirrors what it writes
+                Layer::L2 => ethernet
+                    .or_else(|| EthernetHeader::parse(&pkt))
+                    .map(|header| header.ether_type)
+                    .ok_or_else(|| io::Error::other("frame shorter than an Ethernet header"))?,
+                Layer::L3 => proto.into_pi_field()?,
+            };
 
-        match item {
-            TunPacket(proto, pkt) if self.0 => {
-                let mut pi = Vec::<u8>::with_capacity(4);
+            let mut pi = Vec::<u8>::with_capacity(4);
 
-                // flags is always 0
-                pi.write_u16::<NativeEndian>(0)?;
-                // write the protocol as network byte order
-                pi.write_u16::<NetworkEndian>(proto.into_pi_field()?)?;
+            // flags is always 0
+            pi.write_u16::<NativeEndian>(0)?;
+            // write the protocol as network byte order
+            pi.write_u16::<NetworkEndian>(proto)?;
 
-                dst.put_slice(&pi);
-                dst.put(pkt);
-            }
-            TunPacket(_, pkt) => dst.put(pkt),
+            dst.put_slice(&pi);
+        }
+        if self.2 > 0 {
+            dst.put_slice(&vnet.unwrap_or_default().to_bytes());
+            // a header larger than the basic one, e.g. with num_buffers
+            dst.put_bytes(0, self.2.saturating_sub(VirtioNetHdr::LEN));
         }
+        dst.put(pkt);
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{PacketProtocol, TunPacket, TunPacketCodec};
+    use crate::configuration::Layer;
+    use crate::ethernet::{EthernetHeader, MacAddr, VlanTag, ETHERTYPE_ARP, ETHERTYPE_IPV4};
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn ethernet() {
+        let mut header = EthernetHeader::new(
+            MacAddr::BROADCAST,
+            MacAddr::new(2, 0, 0, 0, 0, 1),
+            ETHERTYPE_IPV4,
+        );
+        header.vlans.push(VlanTag::new(10));
+        let mut frame = header.to_bytes();
+        frame.extend_from_slice(&[0x45, 0, 0, 20]);
+
+        let mut codec = TunPacketCodec::new(true, 1500).with_layer(Layer::L2);
+        let mut buf = BytesMut::new();
+        codec
+            .encode(TunPacket::from_frame(frame.clone()), &mut buf)
+            .unwrap();
+        assert_eq!([0, 0, 0x08, 0x00], buf[..4]);
+
+        let pkt = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(Some(&header), pkt.ethernet());
+        assert!(matches!(pkt.protocol(), PacketProtocol::Ipv4));
+        assert_eq!([0x45, 0, 0, 20], pkt.payload());
+        assert_eq!(frame, pkt.get_bytes());
+
+        // anything else than IP goes through as well
+        frame[16..18].copy_from_slice(&ETHERTYPE_ARP.to_be_bytes());
+        codec
+            .encode(TunPacket::from_frame(frame), &mut buf)
+            .unwrap();
+        assert_eq!([0, 0, 0x08, 0x06], buf[..4]);
+        let pkt = codec.decode(&mut buf).unwrap().unwrap();
+        assert!(matches!(pkt.protocol(), PacketProtocol::Other(0)));
+    }
+}
@@ -0,0 +1,630 @@
+//! A DHCPv4 server for the hosts behind a TAP device, e.g. virtual machines
+//! bridged with it, without running a separate daemon
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, Ipv4Addr},
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+use super::codec::TunPacket;
+use super::neighbor::Replies;
+use super::offload::{checksum, fold, pseudo_header};
+use crate::error::{Error, Result};
+use crate::ethernet::{EthernetHeader, MacAddr, ETHERTYPE_IPV4};
+use crate::network::IpNetwork;
+
+const SERVER_PORT: u16 = 67;
+const CLIENT_PORT: u16 = 68;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+// BOOTP header up to the options, the magic cookie included
+const BOOTP_LEN: usize = 240;
+// some clients drop replies smaller than a BOOTP message
+const MIN_BOOTP_LEN: usize = 300;
+const BROADCAST_FLAG: u16 = 0x8000;
+// how long an offered address is kept for the client to request it
+const OFFER_TIMEOUT: Duration = Duration::from_secs(30);
+
+const OPT_PAD: u8 = 0;
+const OPT_SUBNET_MASK: u8 = 1;
+const OPT_ROUTER: u8 = 3;
+const OPT_DNS: u8 = 6;
+const OPT_REQUESTED_ADDRESS: u8 = 50;
+const OPT_LEASE_TIME: u8 = 51;
+const OPT_MESSAGE_TYPE: u8 = 53;
+const OPT_SERVER_ID: u8 = 54;
+const OPT_END: u8 = 255;
+/// What fits in the 255 bytes of an option
+const MAX_DNS: usize = 63;
+
+const DISCOVER: u8 = 1;
+const OFFER: u8 = 2;
+const REQUEST: u8 = 3;
+const DECLINE: u8 = 4;
+const ACK: u8 = 5;
+const NAK: u8 = 6;
+const RELEASE: u8 = 7;
+const INFORM: u8 = 8;
+
+/// An address handed out, or offered until the client requests it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lease {
+    /// Unspecified for an address a client declined, found in use by some other host
+    pub mac: MacAddr,
+    pub address: Ipv4Addr,
+    pub expires: Instant,
+}
+
+/// Wraps the frames of an L2 device, such as [`AsyncTun::into_framed`](super::tun::AsyncTun::into_framed),
+/// answering the DHCP requests of the hosts behind it. Every other frame is passed through,
+/// in both directions. Unicast renewals need the server address to be resolved, e.g. by
+/// stacking a [`NeighborResponder`](super::neighbor::NeighborResponder) binding it
+pub struct DhcpServer<S> {
+    inner: S,
+    network: IpNetwork,
+    address: Ipv4Addr,
+    mac: MacAddr,
+    pool: (Ipv4Addr, Ipv4Addr),
+    router: Option<Ipv4Addr>,
+    dns: Vec<Ipv4Addr>,
+    lease_time: Duration,
+    leases: HashMap<Ipv4Addr, Lease>,
+    replies: Replies,
+}
+
+/// The fields of a client message the server looks at
+struct Message<'a> {
+    source: MacAddr,
+    ty: u8,
+    bootp: &'a [u8],
+    ciaddr: Ipv4Addr,
+    chaddr: MacAddr,
+    requested: Option<Ipv4Addr>,
+    server_id: Option<Ipv4Addr>,
+}
+
+impl<S> DhcpServer<S> {
+    /// Serve from `network`, whose address is the one of the server, e.g. `10.0.0.1/24`.
+    /// The pool is every other host address of the network unless set
+    pub fn new(inner: S, network: IpNetwork, mac: MacAddr) -> Result<Self> {
+        let (IpAddr::V4(address), IpAddr::V4(base)) = (network.addr(), network.network()) else {
+            return Err(Error::InvalidAddress);
+        };
+        if network.prefix() > 30 {
+            return Err(Error::InvalidPrefix(network.prefix()));
+        }
+
+        let broadcast = u32::from(base) | !u32::from(ipv4(network.netmask()));
+        Ok(Self {
+            inner,
+            network,
+            address,
+            mac,
+            pool: ((u32::from(base) + 1).into(), (broadcast - 1).into()),
+            router: None,
+            dns: Vec::new(),
+            lease_time: Duration::from_secs(3600),
+            leases: HashMap::new(),
+            replies: Replies::default(),
+        })
+    }
+
+    /// Hand out the addresses from `first` to `last` included, those out of the network
+    /// and its network and broadcast addresses are skipped
+    pub fn pool(&mut self, first: Ipv4Addr, last: Ipv4Addr) -> &mut Self {
+        self.pool = (first, last);
+        self
+    }
+
+    pub fn router(&mut self, addr: Ipv4Addr) -> &mut Self {
+        self.router = Some(addr);
+        self
+    }
+
+    /// At most 63 servers are announced, the following ones are ignored
+    pub fn dns<I: IntoIterator<Item = Ipv4Addr>>(&mut self, servers: I) -> &mut Self {
+        self.dns = servers.into_iter().take(MAX_DNS).collect();
+        self
+    }
+
+    /// Longer than `u32::MAX` seconds, the longest a lease can be announced for,
+    /// is the same as `u32::MAX` seconds
+    pub fn lease_time(&mut self, value: Duration) -> &mut Self {
+        self.lease_time = value.min(Duration::from_secs(u32::MAX as u64));
+        self
+    }
+
+    /// The leases and pending offers, expired ones included until their address is reused
+    pub fn leases(&self) -> impl Iterator<Item = &Lease> {
+        self.leases.values()
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Replies not sent yet are lost
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// None if `pkt` is not a DHCP request, the answer if it calls for one otherwise
+    fn handle(&mut self, pkt: &TunPacket, now: Instant) -> Option<Option<TunPacket>> {
+        let msg = Message::parse(pkt)?;
+        let reply = match msg.ty {
+            DISCOVER => self.offer(&msg, now).map(|addr| (OFFER, addr)),
+            REQUEST => self.request(&msg, now),
+            DECLINE | RELEASE => {
+                self.release(&msg, now);
+                None
+            }
+            INFORM => Some((ACK, Ipv4Addr::UNSPECIFIED)),
+            _ => None,
+        };
+
+        Some(reply.map(|(ty, yiaddr)| self.reply(&msg, ty, yiaddr)))
+    }
+
+    fn offer(&mut self, msg: &Message, now: Instant) -> Option<Ipv4Addr> {
+        let addr = match self.lease_of(&msg.chaddr) {
+            Some(lease) => lease.address,
+            None => msg
+                .requested
+                .filter(|addr| self.is_free(addr, now))
+                .or_else(|| self.free_address(now))?,
+        };
+
+        let lease = self.leases.entry(addr).or_insert(Lease {
+            mac: msg.chaddr,
+            address: addr,
+            expires: now,
+        });
+        lease.mac = msg.chaddr;
+        lease.expires = lease.expires.max(now + OFFER_TIMEOUT);
+        Some(addr)
+    }
+
+    fn request(&mut self, msg: &Message, now: Instant) -> Option<(u8, Ipv4Addr)> {
+        match msg.server_id {
+            // the client went with another server
+            Some(id) if id != self.address => return None,
+            _ => {}
+        }
+
+        let addr = msg
+            .requested
+            .or(Some(msg.ciaddr))
+            .filter(|addr| !addr.is_unspecified())?;
+        let ours = self
+            .leases
+            .get(&addr)
+            .is_some_and(|lease| lease.mac == msg.chaddr);
+        if !ours && !self.is_free(&addr, now) {
+            return Some((NAK, Ipv4Addr::UNSPECIFIED));
+        }
+
+        // a former lease of the client is given up for the requested address
+        self.leases.retain(|_, lease| lease.mac != msg.chaddr);
+        self.leases.insert(
+            addr,
+            Lease {
+                mac: msg.chaddr,
+                address: addr,
+                expires: now + self.lease_time,
+            },
+        );
+        Some((ACK, addr))
+    }
+
+    fn release(&mut self, msg: &Message, now: Instant) {
+        let Some(addr) = self.lease_of(&msg.chaddr).map(|lease| lease.address) else {
+            return;
+        };
+
+        if msg.ty == DECLINE {
+            // keep it away from the pool for a while
+            self.leases.insert(
+                addr,
+                Lease {
+                    mac: MacAddr::default(),
+                    address: addr,
+                    expires: now + self.lease_time,
+                },
+            );
+        } else {
+            self.leases.remove(&addr);
+        }
+    }
+
+    fn lease_of(&self, mac: &MacAddr) -> Option<&Lease> {
+        self.leases.values().find(|lease| lease.mac == *mac)
+    }
+
+    fn is_free(&self, addr: &Ipv4Addr, now: Instant) -> bool {
+        let (first, last) = self.pool;
+        let base = u32::from(ipv4(self.network.network()));
+        let broadcast = base | !u32::from(ipv4(self.network.netmask()));
+        (first..=last).contains(addr)
+            && (base + 1..broadcast).contains(&u32::from(*addr))
+            && *addr != self.address
+            && self.router != Some(*addr)
+            && match self.leases.get(addr) {
+                Some(lease) => lease.expires <= now,
+                None => true,
+            }
+    }
+
+    fn free_address(&self, now: Instant) -> Option<Ipv4Addr> {
+        let (first, last) = self.pool;
+        (u32::from(first)..=u32::from(last))
+            .map(Ipv4Addr::from)
+            .find(|addr| self.is_free(addr, now))
+    }
+
+    fn reply(&self, msg: &Message, ty: u8, yiaddr: Ipv4Addr) -> TunPacket {
+        let mut bootp = vec![2, 1, 6, 0];
+        // xid, secs and flags
+        bootp.extend_from_slice(&msg.bootp[4..12]);
+        bootp.extend_from_slice(&msg.bootp[12..16]);
+        bootp.extend_from_slice(&yiaddr.octets());
+        bootp.extend_from_slice(&[0; 4]);
+        // giaddr, chaddr, sname and file
+        bootp.extend_from_slice(&msg.bootp[24..236]);
+        bootp.extend_from_slice(&MAGIC_COOKIE);
+
+        bootp.extend_from_slice(&[OPT_MESSAGE_TYPE, 1, ty]);
+        bootp.extend_from_slice(&[OPT_SERVER_ID, 4]);
+        bootp.extend_from_slice(&self.address.octets());
+        if ty != NAK {
+            if !yiaddr.is_unspecified() {
+                // at most u32::MAX, see `lease_time`
+                let secs = self.lease_time.as_secs() as u32;
+                bootp.extend_from_slice(&[OPT_LEASE_TIME, 4]);
+                bootp.extend_from_slice(&secs.to_be_bytes());
+            }
+            bootp.extend_from_slice(&[OPT_SUBNET_MASK, 4]);
+            bootp.extend_from_slice(&ipv4(self.network.netmask()).octets());
+            if let Some(router) = self.router {
+                bootp.extend_from_slice(&[OPT_ROUTER, 4]);
+                bootp.extend_from_slice(&router.octets());
+            }
+            if !self.dns.is_empty() {
+                // at most 252, see `dns`
+                let len = 4 * self.dns.len();
+                bootp.extend_from_slice(&[OPT_DNS, len as u8]);
+                for server in self.dns.iter() {
+                    bootp.extend_from_slice(&server.octets());
+                }
+            }
+        }
+        bootp.push(OPT_END);
+        bootp.resize(bootp.len().max(MIN_BOOTP_LEN), OPT_PAD);
+
+        // RFC 2131 4.1, the client may not have its address yet
+        let flags = u16::from_be_bytes([msg.bootp[10], msg.bootp[11]]);
+        let (mac, ip) = if ty == NAK || flags & BROADCAST_FLAG != 0 {
+            (MacAddr::BROADCAST, Ipv4Addr::BROADCAST)
+        } else if !msg.ciaddr.is_unspecified() {
+            (msg.source, msg.ciaddr)
+        } else {
+            (msg.source, yiaddr)
+        };
+
+        let mut frame = EthernetHeader::new(mac, self.mac, ETHERTYPE_IPV4).to_bytes();
+        frame.extend_from_slice(&udp(self.address, ip, &bootp));
+        TunPacket::from_frame(frame)
+    }
+}
+
+impl<'a> Message<'a> {
+    fn parse(pkt: &'a TunPacket) -> Option<Self> {
+        let header = pkt
+            .ethernet()
+            .filter(|header| header.ether_type == ETHERTYPE_IPV4)?;
+        let ip = pkt.payload();
+        if ip.len() < 20 || ip[0] >> 4 != 4 || ip[9] != libc::IPPROTO_UDP as u8 {
+            return None;
+        }
+
+        let udp = ip.get((ip[0] & 0x0f) as usize * 4..)?;
+        if udp.len() < 8 || u16::from_be_bytes([udp[2], udp[3]]) != SERVER_PORT {
+            return None;
+        }
+
+        let bootp = &udp[8..];
+        if bootp.len() < BOOTP_LEN || bootp[..3] != [1, 1, 6] || bootp[236..240] != MAGIC_COOKIE {
+            return None;
+        }
+
+        let mut msg = Message {
+            source: header.source,
+            ty: 0,
+            bootp,
+            ciaddr: Ipv4Addr::new(bootp[12], bootp[13], bootp[14], bootp[15]),
+            chaddr: MacAddr(bootp[28..34].try_into().unwrap()),
+            requested: None,
+            server_id: None,
+        };
+
+        let mut options = &bootp[BOOTP_LEN..];
+        while let &[code, ref rest @ ..] = options {
+            match code {
+                OPT_PAD => options = rest,
+                OPT_END => break,
+                _ => {
+                    let (&len, rest) = rest.split_first()?;
+                    let data = rest.get(..len as usize)?;
+                    match (code, data) {
+                        (OPT_MESSAGE_TYPE, &[ty]) => msg.ty = ty,
+                        (OPT_REQUESTED_ADDRESS, &[a, b, c, d]) => {
+                            msg.requested = Some(Ipv4Addr::new(a, b, c, d))
+                        }
+                        (OPT_SERVER_ID, &[a, b, c, d]) => {
+                            msg.server_id = Some(Ipv4Addr::new(a, b, c, d))
+                        }
+                        _ => {}
+                    }
+                    options = &rest[len as usize..];
+                }
+            }
+        }
+
+        Some(msg)
+    }
+}
+
+/// An IPv4 packet carrying `payload` from the server port to the client one
+fn udp(source: Ipv4Addr, destination: Ipv4Addr, payload: &[u8]) -> Vec<u8> {
+    let udp_len = 8 + payload.len();
+    let total = 20 + udp_len;
+
+    let mut pkt = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 64, libc::IPPROTO_UDP as u8, 0, 0];
+    pkt[2..4].copy_from_slice(&(total as u16).to_be_bytes());
+    pkt.extend_from_slice(&source.octets());
+    pkt.extend_from_slice(&destination.octets());
+    let csum = !fold(checksum(&pkt, 0));
+    pkt[10..12].copy_from_slice(&csum.to_be_bytes());
+
+    pkt.extend_from_slice(&SERVER_PORT.to_be_bytes());
+    pkt.extend_from_slice(&CLIENT_PORT.to_be_bytes());
+    pkt.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    pkt.extend_from_slice(&[0, 0]);
+    pkt.extend_from_slice(payload);
+
+    let pseudo = pseudo_header(&pkt, true, libc::IPPROTO_UDP as u8, udp_len);
+    // zero means no checksum for UDP over IPv4
+    let csum = match !fold(checksum(&pkt[20..], pseudo)) {
+        0 => 0xffff,
+        csum => csum,
+    };
+    pkt[26..28].copy_from_slice(&csum.to_be_bytes());
+    pkt
+}
+
+fn ipv4(addr: IpAddr) -> Ipv4Addr {
+    match addr {
+        IpAddr::V4(addr) => addr,
+        IpAddr::V6(_) => unreachable!(),
+    }
+}
+
+impl<S> Stream for DhcpServer<S>
+where
+    S: Stream<Item = io::Result<TunPacket>> + Sink<TunPacket, Error = io::Error> + Unpin,
+{
+    type Item = io::Result<TunPacket>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            // replies are sent along the reads, a pending write wakes us up again
+            if let Poll::Ready(Err(err)) = this.replies.poll_send(&mut this.inner, cx) {
+                return Poll::Ready(Some(Err(err)));
+            }
+
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(Ok(pkt)) => match this.handle(&pkt, Instant::now()) {
+                    Some(Some(reply)) => this.replies.push(reply),
+                    Some(None) => {}
+                    None => return Poll::Ready(Some(Ok(pkt))),
+                },
+                other => return Poll::Ready(other),
+            }
+        }
+    }
+}
+
+impl<S> Sink<TunPacket> for DhcpServer<S>
+where
+    S: Sink<TunPacket, Error = io::Error> + Unpin,
+{
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.replies.poll_send(&mut this.inner, cx))?;
+        Pin::new(&mut this.inner).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: TunPacket) -> io::Result<()> {
+        Pin::new(&mut self.get_mut().inner).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.replies.poll_send(&mut this.inner, cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.replies.poll_send(&mut this.inner, cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn message(ty: u8, chaddr: MacAddr, requested: Option<Ipv4Addr>) -> TunPacket {
+        let mut bootp = vec![1, 1, 6, 0, 0xde, 0xad, 0xbe, 0xef, 0, 0, 0x80, 0];
+        bootp.resize(28, 0);
+        bootp.extend_from_slice(&chaddr.0);
+        bootp.resize(236, 0);
+        bootp.extend_from_slice(&MAGIC_COOKIE);
+        bootp.extend_from_slice(&[OPT_MESSAGE_TYPE, 1, ty]);
+        if let Some(addr) = requested {
+            bootp.extend_from_slice(&[OPT_REQUESTED_ADDRESS, 4]);
+            bootp.extend_from_slice(&addr.octets());
+        }
+        bootp.push(OPT_END);
+
+        let mut udp = vec![0, 68, 0, 67, 0, 0, 0, 0];
+        udp.extend_from_slice(&bootp);
+        let mut ip = vec![
+            0x45, 0, 0, 0, 0, 0, 0, 0, 64, 17, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255,
+        ];
+        ip.extend_from_slice(&udp);
+
+        let mut frame = EthernetHeader::new(MacAddr::BROADCAST, chaddr, ETHERTYPE_IPV4).to_bytes();
+        frame.extend_from_slice(&ip);
+        TunPacket::from_frame(frame)
+    }
+
+    /// The message type and the address of a reply
+    fn answer(reply: TunPacket) -> (u8, Ipv4Addr) {
+        let ip = reply.payload();
+        assert_eq!(0xffff, fold(checksum(&ip[..20], 0)));
+        let pseudo = pseudo_header(ip, true, 17, ip.len() - 20);
+        assert_eq!(0xffff, fold(checksum(&ip[20..], pseudo)));
+
+        let bootp = &ip[28..];
+        assert_eq!(MIN_BOOTP_LEN, bootp.len());
+        assert_eq!([OPT_MESSAGE_TYPE, 1], bootp[240..242]);
+        (
+            bootp[242],
+            Ipv4Addr::new(bootp[16], bootp[17], bootp[18], bootp[19]),
+        )
+    }
+
+    #[test]
+    fn leases() {
+        let network = "10.0.0.1/24".parse().unwrap();
+        let mut server = DhcpServer::new((), network, MacAddr::new(2, 0, 0, 0, 0, 1)).unwrap();
+        server
+            .pool(Ipv4Addr::new(10, 0, 0, 100), Ipv4Addr::new(10, 0, 0, 101))
+            .router(Ipv4Addr::new(10, 0, 0, 1));
+
+        let now = Instant::now();
+        let (a, b, c) = (
+            MacAddr::new(2, 0, 0, 0, 0, 0xa),
+            MacAddr::new(2, 0, 0, 0, 0, 0xb),
+            MacAddr::new(2, 0, 0, 0, 0, 0xc),
+        );
+        let first = Ipv4Addr::new(10, 0, 0, 100);
+        let second = Ipv4Addr::new(10, 0, 0, 101);
+
+        let offer = server.handle(&message(DISCOVER, a, None), now).unwrap();
+        assert_eq!((OFFER, first), answer(offer.unwrap()));
+        let ack = server
+            .handle(&message(REQUEST, a, Some(first)), now)
+            .unwrap();
+        assert_eq!((ACK, first), answer(ack.unwrap()));
+
+        // the lease is kept for the client, the other one gets the next address
+        let offer = server.handle(&message(DISCOVER, a, None), now).unwrap();
+        assert_eq!((OFFER, first), answer(offer.unwrap()));
+        let nak = server
+            .handle(&message(REQUEST, b, Some(first)), now)
+            .unwrap();
+        assert_eq!(NAK, answer(nak.unwrap()).0);
+        let offer = server
+            .handle(&message(DISCOVER, b, Some(first)), now)
+            .unwrap();
+        assert_eq!((OFFER, second), answer(offer.unwrap()));
+
+        // exhausted until an address comes back
+        assert!(server
+            .handle(&message(DISCOVER, c, None), now)
+            .unwrap()
+            .is_none());
+        assert!(server
+            .handle(&message(RELEASE, a, None), now)
+            .unwrap()
+            .is_none());
+        let offer = server.handle(&message(DISCOVER, c, None), now).unwrap();
+        assert_eq!((OFFER, first), answer(offer.unwrap()));
+
+        // not for the server
+        let mut pkt = message(DISCOVER, c, None).into_bytes().to_vec();
+        pkt[14 + 22..14 + 24].copy_from_slice(&[0, 53]);
+        assert!(server.handle(&TunPacket::from_frame(pkt), now).is_none());
+    }
+
+    #[test]
+    fn dns() {
+        let network = "10.0.0.1/24".parse().unwrap();
+        let mut server = DhcpServer::new((), network, MacAddr::new(2, 0, 0, 0, 0, 1)).unwrap();
+        server.dns((0..100).map(|i| Ipv4Addr::new(10, 0, 1, i)));
+
+        let chaddr = MacAddr::new(2, 0, 0, 0, 0, 0xa);
+        let offer = server.handle(&message(DISCOVER, chaddr, None), Instant::now());
+        let offer = offer.unwrap().unwrap();
+        let bootp = &offer.payload()[28..];
+        let mut at = BOOTP_LEN;
+        while bootp[at] != OPT_DNS {
+            at += 2 + bootp[at + 1] as usize;
+        }
+        assert_eq!(252, bootp[at + 1]);
+        assert_eq!([10, 0, 1, 62], bootp[at + 250..at + 254]);
+        assert_eq!(OPT_END, bootp[at + 254]);
+    }
+
+    #[test]
+    fn pool_bounds() {
+        let network = "10.0.0.1/24".parse().unwrap();
+        let mut server = DhcpServer::new((), network, MacAddr::new(2, 0, 0, 0, 0, 1)).unwrap();
+        server.pool(Ipv4Addr::new(10, 0, 0, 0), Ipv4Addr::new(10, 0, 0, 255));
+
+        let now = Instant::now();
+        assert!(!server.is_free(&Ipv4Addr::new(10, 0, 0, 0), now));
+        assert!(!server.is_free(&Ipv4Addr::new(10, 0, 0, 1), now));
+        assert!(server.is_free(&Ipv4Addr::new(10, 0, 0, 254), now));
+        assert!(!server.is_free(&Ipv4Addr::new(10, 0, 0, 255), now));
+
+        let chaddr = MacAddr::new(2, 0, 0, 0, 0, 0xa);
+        let offer = server
+            .handle(&message(DISCOVER, chaddr, None), now)
+            .unwrap();
+        assert_eq!((OFFER, Ipv4Addr::new(10, 0, 0, 2)), answer(offer.unwrap()));
+    }
+
+    #[test]
+    fn lease_time() {
+        let network = "10.0.0.1/24".parse().unwrap();
+        let mut server = DhcpServer::new((), network, MacAddr::new(2, 0, 0, 0, 0, 1)).unwrap();
+        server.lease_time(Duration::MAX);
+
+        let chaddr = MacAddr::new(2, 0, 0, 0, 0, 0xa);
+        let requested = Some(Ipv4Addr::new(10, 0, 0, 2));
+        let ack = server.handle(&message(REQUEST, chaddr, requested), Instant::now());
+        let ack = ack.unwrap().unwrap();
+        let bootp = &ack.payload()[28..];
+        assert_eq!(ACK, bootp[242]);
+        let mut at = BOOTP_LEN;
+        while bootp[at] != OPT_LEASE_TIME {
+            at += 2 + bootp[at + 1] as usize;
+        }
+        assert_eq!(u32::MAX.to_be_bytes(), bootp[at + 2..at + 6]);
+    }
+}
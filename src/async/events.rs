@@ -0,0 +1,56 @@
+use futures_core::Stream;
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::unix::AsyncFd;
+
+use crate::error::Result;
+use crate::tun::{Event, Events};
+
+/// The changes of a device as a [`Stream`], see [`AsyncTun::events`](super::tun::AsyncTun::events)
+pub struct AsyncEvents {
+    inner: AsyncFd<Events>,
+}
+
+impl AsyncEvents {
+    pub fn new(events: Events) -> Result<AsyncEvents> {
+        events.set_nonblocking(true)?;
+
+        // SAFETY: the netlink socket is owned by `events` and only closed when it is dropped
+        let inner = unsafe { AsyncFd::register(events) }.map_err(io::Error::from)?;
+        Ok(AsyncEvents { inner })
+    }
+
+    pub fn get_ref(&self) -> &Events {
+        self.inner.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut Events {
+        self.inner.get_mut()
+    }
+}
+
+impl Stream for AsyncEvents {
+    type Item = Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.inner.get_mut().pop() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if this.inner.get_ref().is_removed() {
+                return Poll::Ready(None);
+            }
+
+            let mut guard = ready!(this.inner.poll_read_ready_mut(cx))?;
+            match guard.try_io(|inner| inner.get_mut().recv()) {
+                Ok(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                Ok(Ok(())) => continue,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
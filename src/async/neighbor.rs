@@ -0,0 +1,312 @@
+//! Answer ARP requests and IPv6 neighbor solicitations on behalf of addresses that
+//! only exist in userspace, e.g. the gateway of a router built over a TAP device
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use super::codec::TunPacket;
+use super::offload::{checksum, fold, pseudo_header};
+use crate::ethernet::{EthernetHeader, MacAddr, ETHERTYPE_ARP, ETHERTYPE_IPV6};
+
+const ARP_REQUEST: u16 = 1;
+const ARP_REPLY: u16 = 2;
+const IPPROTO_ICMPV6: u8 = 58;
+const NEIGHBOR_SOLICITATION: u8 = 135;
+const NEIGHBOR_ADVERTISEMENT: u8 = 136;
+const NA_ROUTER: u8 = 0x80;
+const NA_SOLICITED: u8 = 0x40;
+const NA_OVERRIDE: u8 = 0x20;
+const OPT_TARGET_LL_ADDR: u8 = 2;
+const ALL_NODES: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+
+/// Wraps the frames of an L2 device, such as [`AsyncTun::into_framed`](super::tun::AsyncTun::into_framed),
+/// answering the ARP requests and neighbor solicitations for the bound addresses.
+/// Every other frame is passed through, in both directions
+pub struct NeighborResponder<S> {
+    inner: S,
+    bindings: HashMap<IpAddr, MacAddr>,
+    router: bool,
+    replies: Replies,
+}
+
+impl<S> NeighborResponder<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            bindings: HashMap::new(),
+            router: false,
+            replies: Replies::default(),
+        }
+    }
+
+    /// Answer for `ip` with `mac`, replacing any previous binding of `ip`
+    pub fn bind<A: Into<IpAddr>>(&mut self, ip: A, mac: MacAddr) -> &mut Self {
+        self.bindings.insert(ip.into(), mac);
+        self
+    }
+
+    pub fn unbind<A: Into<IpAddr>>(&mut self, ip: A) -> Option<MacAddr> {
+        self.bindings.remove(&ip.into())
+    }
+
+    pub fn bindings(&self) -> &HashMap<IpAddr, MacAddr> {
+        &self.bindings
+    }
+
+    /// Set the router flag of the neighbor advertisements, for gateway addresses
+    pub fn router(&mut self, value: bool) -> &mut Self {
+        self.router = value;
+        self
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Replies not sent yet are lost
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// The answer to `pkt` if it asks for one of the bindings
+    fn reply(&self, pkt: &TunPacket) -> Option<TunPacket> {
+        let header = pkt.ethernet()?;
+        let (mac, destination, body) = match header.ether_type {
+            ETHERTYPE_ARP => {
+                let target = arp_request(pkt.payload())?;
+                let mac = self.bindings.get(&target.into())?;
+                (*mac, header.source, arp_reply(pkt.payload(), mac))
+            }
+            ETHERTYPE_IPV6 => {
+                let target = neighbor_solicitation(pkt.payload())?;
+                let mac = self.bindings.get(&target.into())?;
+                let na = self.neighbor_advertisement(pkt.payload(), target, mac);
+                // duplicate address detection is answered to all nodes
+                let destination = match na[24..40] == ALL_NODES.octets() {
+                    true => MacAddr::new(0x33, 0x33, 0, 0, 0, 1),
+                    false => header.source,
+                };
+                (*mac, destination, na)
+            }
+            _ => return None,
+        };
+
+        let reply = EthernetHeader {
+            destination,
+            source: mac,
+            vlans: header.vlans.clone(),
+            ether_type: header.ether_type,
+        };
+        let mut frame = reply.to_bytes();
+        frame.extend_from_slice(&body);
+        Some(TunPacket::from_frame(frame))
+    }
+
+    fn neighbor_advertisement(&self, ns: &[u8], target: Ipv6Addr, mac: &MacAddr) -> Vec<u8> {
+        let source = Ipv6Addr::from(<[u8; 16]>::try_from(&ns[8..24]).unwrap());
+        let (destination, mut flags) = match source.is_unspecified() {
+            true => (ALL_NODES, NA_OVERRIDE),
+            false => (source, NA_SOLICITED | NA_OVERRIDE),
+        };
+        if self.router {
+            flags |= NA_ROUTER;
+        }
+
+        let mut na = vec![0x60, 0, 0, 0, 0, 32, IPPROTO_ICMPV6, 255];
+        na.extend_from_slice(&target.octets());
+        na.extend_from_slice(&destination.octets());
+        na.extend_from_slice(&[NEIGHBOR_ADVERTISEMENT, 0, 0, 0, flags, 0, 0, 0]);
+        na.extend_from_slice(&target.octets());
+        na.extend_from_slice(&[OPT_TARGET_LL_ADDR, 1]);
+        na.extend_from_slice(&mac.0);
+
+        let csum = !fold(checksum(
+            &na[40..],
+            pseudo_header(&na, false, IPPROTO_ICMPV6, 32),
+        ));
+        na[42..44].copy_from_slice(&csum.to_be_bytes());
+        na
+    }
+}
+
+/// The target of an Ethernet/IPv4 ARP request
+fn arp_request(arp: &[u8]) -> Option<Ipv4Addr> {
+    if arp.len() < 28 || arp[..8] != [0, 1, 8, 0, 6, 4, 0, ARP_REQUEST as u8] {
+        return None;
+    }
+
+    Some(Ipv4Addr::new(arp[24], arp[25], arp[26], arp[27]))
+}
+
+fn arp_reply(request: &[u8], mac: &MacAddr) -> Vec<u8> {
+    let mut arp = request[..6].to_vec();
+    arp.extend_from_slice(&ARP_REPLY.to_be_bytes());
+    arp.extend_from_slice(&mac.0);
+    // the target address of the request
+    arp.extend_from_slice(&request[24..28]);
+    // back to the sender
+    arp.extend_from_slice(&request[8..18]);
+    arp
+}
+
+/// The target of a neighbor solicitation, extension headers are not followed
+fn neighbor_solicitation(ip: &[u8]) -> Option<Ipv6Addr> {
+    // RFC 4861 7.1.1, the hop limit proves the packet was not forwarded
+    if ip.len() < 64 || ip[0] >> 4 != 6 || ip[6] != IPPROTO_ICMPV6 || ip[7] != 255 {
+        return None;
+    }
+    if ip[40] != NEIGHBOR_SOLICITATION || ip[41] != 0 {
+        return None;
+    }
+
+    let target = Ipv6Addr::from(<[u8; 16]>::try_from(&ip[48..64]).unwrap());
+    (!target.is_multicast()).then_some(target)
+}
+
+/// Frames answered on behalf of the application, waiting for the sink they are injected into
+#[derive(Default)]
+pub(super) struct Replies {
+    queue: VecDeque<TunPacket>,
+    unflushed: bool,
+}
+
+impl Replies {
+    pub fn push(&mut self, reply: TunPacket) {
+        self.queue.push_back(reply);
+    }
+
+    /// Hand the pending replies over to `sink`
+    pub fn poll_send<S>(&mut self, sink: &mut S, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        S: Sink<TunPacket, Error = io::Error> + Unpin,
+    {
+        while let Some(reply) = self.queue.pop_front() {
+            if Pin::new(&mut *sink).poll_ready(cx)?.is_pending() {
+                self.queue.push_front(reply);
+                return Poll::Pending;
+            }
+            Pin::new(&mut *sink).start_send(reply)?;
+            self.unflushed = true;
+        }
+
+        if self.unflushed {
+            ready!(Pin::new(sink).poll_flush(cx))?;
+            self.unflushed = false;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> Stream for NeighborResponder<S>
+where
+    S: Stream<Item = io::Result<TunPacket>> + Sink<TunPacket, Error = io::Error> + Unpin,
+{
+    type Item = io::Result<TunPacket>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            // replies are sent along the reads, a pending write wakes us up again
+            if let Poll::Ready(Err(err)) = this.replies.poll_send(&mut this.inner, cx) {
+                return Poll::Ready(Some(Err(err)));
+            }
+
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(Ok(pkt)) => match this.reply(&pkt) {
+                    Some(reply) => this.replies.push(reply),
+                    None => return Poll::Ready(Some(Ok(pkt))),
+                },
+                other => return Poll::Ready(other),
+            }
+        }
+    }
+}
+
+impl<S> Sink<TunPacket> for NeighborResponder<S>
+where
+    S: Sink<TunPacket, Error = io::Error> + Unpin,
+{
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.replies.poll_send(&mut this.inner, cx))?;
+        Pin::new(&mut this.inner).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: TunPacket) -> io::Result<()> {
+        Pin::new(&mut self.get_mut().inner).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.replies.poll_send(&mut this.inner, cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.replies.poll_send(&mut this.inner, cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn neighbor_advertisement() {
+        let mac = MacAddr::new(2, 0, 0, 0, 0, 0x18);
+        let mut responder = NeighborResponder::new(());
+        responder.bind("fd00::1".parse::<Ipv6Addr>().unwrap(), mac);
+
+        let source: Ipv6Addr = "fd00::2".parse().unwrap();
+        let target: Ipv6Addr = "fd00::1".parse().unwrap();
+        let host = MacAddr::new(2, 0, 0, 0, 0, 2);
+        let mut frame = EthernetHeader::new(
+            MacAddr::new(0x33, 0x33, 0xff, 0, 0, 1),
+            host,
+            ETHERTYPE_IPV6,
+        )
+        .to_bytes();
+        frame.extend_from_slice(&[0x60, 0, 0, 0, 0, 24, IPPROTO_ICMPV6, 255]);
+        frame.extend_from_slice(&source.octets());
+        frame.extend_from_slice(&"ff02::1:ff00:1".parse::<Ipv6Addr>().unwrap().octets());
+        frame.extend_from_slice(&[NEIGHBOR_SOLICITATION, 0, 0, 0, 0, 0, 0, 0]);
+        frame.extend_from_slice(&target.octets());
+
+        let reply = responder
+            .reply(&TunPacket::from_frame(frame.clone()))
+            .unwrap();
+        let header = reply.ethernet().unwrap();
+        assert_eq!((host, mac), (header.destination, header.source));
+
+        let na = reply.payload();
+        assert_eq!(72, na.len());
+        assert_eq!(target.octets(), na[8..24]);
+        assert_eq!(source.octets(), na[24..40]);
+        assert_eq!(
+            [NEIGHBOR_ADVERTISEMENT, NA_SOLICITED | NA_OVERRIDE],
+            [na[40], na[44]]
+        );
+        assert_eq!(mac.0, na[66..72]);
+        let pseudo = pseudo_header(na, false, IPPROTO_ICMPV6, 32);
+        assert_eq!(0xffff, fold(checksum(&na[40..], pseudo)));
+
+        // not forwarded
+        frame[14 + 7] = 64;
+        assert!(responder.reply(&TunPacket::from_frame(frame)).is_none());
+    }
+}
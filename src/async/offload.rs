@@ -0,0 +1,509 @@
+//! Userspace segmentation and coalescing of L3 packets for devices with offloads,
+//! see [`TunConf::offloads`](crate::tun::TunConf::offloads)
+
+use bytes::{BufMut, BytesMut};
+use std::io;
+
+use super::codec::TunPacket;
+use crate::vnet::{GsoType, VirtioNetHdr, VIRTIO_NET_HDR_F_NEEDS_CSUM};
+
+const TCP_FIN: u8 = 0x01;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+const TCP_CWR: u8 = 0x80;
+
+/// Split a packet read with a GSO virtio-net header into packets of at most `gso_size`
+/// payload bytes, the IP IDs, TCP sequence numbers, lengths and checksums are updated.
+/// A packet without GSO is returned as is, its checksum completed if the kernel left it to us
+pub fn gso_segment(pkt: TunPacket) -> io::Result<Vec<TunPacket>> {
+    let vnet = match pkt.vnet_hdr() {
+        Some(vnet) => *vnet,
+        None => return Ok(vec![pkt]),
+    };
+    let data = pkt.get_bytes();
+
+    if !vnet.is_gso() {
+        let mut data = BytesMut::from(data);
+        if vnet.needs_csum() {
+            complete_csum(&mut data, &vnet)?;
+        }
+        return Ok(vec![TunPacket::new(data.freeze())]);
+    }
+
+    let headers = Headers::parse(data).ok_or_else(|| invalid("unsupported packet"))?;
+    match (vnet.gso_type, headers.proto) {
+        (GsoType::TcpV4, libc::IPPROTO_TCP) if headers.v4 => {}
+        (GsoType::TcpV6, libc::IPPROTO_TCP) if !headers.v4 => {}
+        (GsoType::UdpL4, libc::IPPROTO_UDP) => {}
+        (gso_type, _) => return Err(invalid(&format!("unsupported segmentation {gso_type:?}"))),
+    }
+    if vnet.gso_size == 0 {
+        return Err(invalid("zero gso_size"));
+    }
+
+    let len = headers.len();
+    let gso_size = vnet.gso_size as usize;
+    let payload = &data[len..];
+    // headers without payload, e.g. a bare FIN, still make one packet
+    let count = payload.len().div_ceil(gso_size).max(1);
+    let chunks = payload
+        .chunks(gso_size)
+        .chain(payload.is_empty().then_some(payload));
+
+    let mut segments = Vec::with_capacity(count);
+    for (i, chunk) in chunks.enumerate() {
+        let mut seg = BytesMut::with_capacity(len + chunk.len());
+        seg.put_slice(&data[..len]);
+        seg.put_slice(chunk);
+
+        if headers.v4 {
+            let id = u16::from_be_bytes([seg[4], seg[5]]).wrapping_add(i as u16);
+            seg[4..6].copy_from_slice(&id.to_be_bytes());
+        }
+
+        let l4 = headers.ip_len;
+        if headers.proto == libc::IPPROTO_TCP {
+            let seq = read_u32(&seg[l4 + 4..]).wrapping_add((i * gso_size) as u32);
+            seg[l4 + 4..l4 + 8].copy_from_slice(&seq.to_be_bytes());
+            if i + 1 < count {
+                seg[l4 + 13] &= !(TCP_FIN | TCP_PSH);
+            }
+            if i > 0 {
+                seg[l4 + 13] &= !TCP_CWR;
+            }
+        }
+
+        headers.finish(&mut seg, false);
+        segments.push(TunPacket::new(seg.freeze()));
+    }
+
+    Ok(segments)
+}
+
+/// Coalesce consecutive TCP segments, and UDP datagrams if `udp` is set, of the same
+/// flow into packets with a GSO virtio-net header, to be written to a device with the
+/// matching offloads. Packets which can not be coalesced are passed through unchanged
+pub fn gro_coalesce<I: IntoIterator<Item = TunPacket>>(pkts: I, udp: bool) -> Vec<TunPacket> {
+    let mut coalesced = Vec::new();
+    let mut run: Option<Run> = None;
+
+    for pkt in pkts {
+        let headers = match Headers::coalescable(&pkt, udp) {
+            Some(headers) => headers,
+            None => {
+                coalesced.extend(run.take().map(Run::finish));
+                coalesced.push(pkt);
+                continue;
+            }
+        };
+
+        match run.as_mut() {
+            Some(current) if current.accepts(&headers, pkt.get_bytes()) => {
+                current.push(&headers, pkt.get_bytes())
+            }
+            _ => {
+                coalesced.extend(run.take().map(Run::finish));
+                run = Some(Run::new(headers, pkt));
+            }
+        }
+    }
+    coalesced.extend(run.map(Run::finish));
+
+    coalesced
+}
+
+/// The IP and transport headers of a TCP or UDP packet
+#[derive(Debug, Clone, Copy)]
+struct Headers {
+    v4: bool,
+    proto: i32,
+    ip_len: usize,
+    l4_len: usize,
+}
+
+impl Headers {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let (v4, proto, ip_len) = match data.first()? >> 4 {
+            4 => (true, *data.get(9)?, (data[0] & 0x0f) as usize * 4),
+            // extension headers are not supported
+            6 => (false, *data.get(6)?, 40),
+            _ => return None,
+        };
+
+        let l4_len = match proto as i32 {
+            libc::IPPROTO_TCP => (*data.get(ip_len + 12)? >> 4) as usize * 4,
+            libc::IPPROTO_UDP => 8,
+            _ => return None,
+        };
+
+        let headers = Self {
+            v4,
+            proto: proto as i32,
+            ip_len,
+            l4_len,
+        };
+        (ip_len >= 20 && l4_len >= 8 && data.len() >= headers.len()).then_some(headers)
+    }
+
+    /// A plain, unfragmented packet with a payload and no TCP flag ending the flow
+    fn coalescable(pkt: &TunPacket, udp: bool) -> Option<Self> {
+        if pkt
+            .vnet_hdr()
+            .is_some_and(|vnet| vnet.is_gso() || vnet.needs_csum())
+        {
+            return None;
+        }
+
+        let data = pkt.get_bytes();
+        let headers = Self::parse(data)?;
+        if data.len() == headers.len() {
+            return None;
+        }
+
+        let valid = if headers.v4 {
+            headers.ip_len == 20
+                && u16::from_be_bytes([data[6], data[7]]) & 0x3fff == 0
+                && u16::from_be_bytes([data[2], data[3]]) as usize == data.len()
+        } else {
+            u16::from_be_bytes([data[4], data[5]]) as usize + 40 == data.len()
+        };
+
+        let valid = valid
+            && match headers.proto {
+                libc::IPPROTO_TCP => data[headers.ip_len + 13] & !(TCP_ACK | TCP_PSH) == 0,
+                _ => udp,
+            };
+
+        valid.then_some(headers)
+    }
+
+    fn len(&self) -> usize {
+        self.ip_len + self.l4_len
+    }
+
+    /// The offset of the transport checksum
+    fn csum_offset(&self) -> usize {
+        match self.proto {
+            libc::IPPROTO_TCP => 16,
+            _ => 6,
+        }
+    }
+
+    /// Update the lengths and checksums, only the pseudo header sum is left in the
+    /// transport checksum if `partial`, as expected along VIRTIO_NET_HDR_F_NEEDS_CSUM
+    fn finish(&self, pkt: &mut [u8], partial: bool) {
+        let l4 = self.ip_len;
+        let l4_len = pkt.len() - l4;
+
+        if self.v4 {
+            let len = pkt.len() as u16;
+            pkt[2..4].copy_from_slice(&len.to_be_bytes());
+            pkt[10..12].fill(0);
+            let csum = !fold(checksum(&pkt[..l4], 0));
+            pkt[10..12].copy_from_slice(&csum.to_be_bytes());
+        } else {
+            pkt[4..6].copy_from_slice(&(l4_len as u16).to_be_bytes());
+        }
+
+        if self.proto == libc::IPPROTO_UDP {
+            pkt[l4 + 4..l4 + 6].copy_from_slice(&(l4_len as u16).to_be_bytes());
+        }
+
+        let at = l4 + self.csum_offset();
+        pkt[at..at + 2].fill(0);
+        let pseudo = pseudo_header(pkt, self.v4, self.proto as u8, l4_len);
+        let csum = if partial {
+            fold(pseudo)
+        } else {
+            match !fold(checksum(&pkt[l4..], pseudo)) {
+                // zero means no checksum for UDP over IPv4
+                0 if self.proto == libc::IPPROTO_UDP => 0xffff,
+                csum => csum,
+            }
+        };
+        pkt[at..at + 2].copy_from_slice(&csum.to_be_bytes());
+    }
+}
+
+/// Packets of the same flow being coalesced
+struct Run {
+    headers: Headers,
+    first: TunPacket,
+    data: Option<BytesMut>,
+    gso_size: usize,
+    next_seq: u32,
+    done: bool,
+}
+
+impl Run {
+    fn new(headers: Headers, first: TunPacket) -> Self {
+        let data = first.get_bytes();
+        let gso_size = data.len() - headers.len();
+
+        Self {
+            headers,
+            next_seq: Self::seq(&headers, data).wrapping_add(gso_size as u32),
+            done: Self::pushed(&headers, data),
+            first,
+            data: None,
+            gso_size,
+        }
+    }
+
+    fn seq(headers: &Headers, data: &[u8]) -> u32 {
+        match headers.proto {
+            libc::IPPROTO_TCP => read_u32(&data[headers.ip_len + 4..]),
+            _ => 0,
+        }
+    }
+
+    fn pushed(headers: &Headers, data: &[u8]) -> bool {
+        headers.proto == libc::IPPROTO_TCP && data[headers.ip_len + 13] & TCP_PSH != 0
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.data.as_deref().unwrap_or(self.first.get_bytes())
+    }
+
+    fn accepts(&self, headers: &Headers, data: &[u8]) -> bool {
+        let current = self.bytes();
+        let payload = data.len() - headers.len();
+
+        if self.done
+            || headers.v4 != self.headers.v4
+            || headers.proto != self.headers.proto
+            || headers.l4_len != self.headers.l4_len
+            || payload > self.gso_size
+            || current.len() + payload > u16::MAX as usize
+            || (headers.proto == libc::IPPROTO_TCP && Self::seq(headers, data) != self.next_seq)
+        {
+            return false;
+        }
+
+        // everything but the lengths, IDs, sequence numbers, PSH and checksums
+        let ip = if headers.v4 {
+            current[..2] == data[..2]
+                && current[6..10] == data[6..10]
+                && current[12..20] == data[12..20]
+        } else {
+            current[..4] == data[..4] && current[6..40] == data[6..40]
+        };
+
+        let (l4, a, b) = (headers.ip_len, current, data);
+        let transport = match headers.proto {
+            libc::IPPROTO_TCP => {
+                a[l4..l4 + 4] == b[l4..l4 + 4]
+                    && a[l4 + 8..l4 + 13] == b[l4 + 8..l4 + 13]
+                    && a[l4 + 13] & !TCP_PSH == b[l4 + 13] & !TCP_PSH
+                    && a[l4 + 14..l4 + 16] == b[l4 + 14..l4 + 16]
+                    && a[l4 + 18..l4 + headers.l4_len] == b[l4 + 18..l4 + headers.l4_len]
+            }
+            _ => a[l4..l4 + 4] == b[l4..l4 + 4],
+        };
+
+        ip && transport
+    }
+
+    fn push(&mut self, headers: &Headers, data: &[u8]) {
+        let payload = &data[headers.len()..];
+        let first = self.first.get_bytes();
+        let buf = self.data.get_or_insert_with(|| BytesMut::from(first));
+        buf.put_slice(payload);
+
+        if Self::pushed(headers, data) {
+            buf[headers.ip_len + 13] |= TCP_PSH;
+        }
+
+        self.next_seq = self.next_seq.wrapping_add(payload.len() as u32);
+        self.done = payload.len() < self.gso_size || Self::pushed(headers, data);
+    }
+
+    fn finish(self) -> TunPacket {
+        let mut data = match self.data {
+            Some(data) => data,
+            None => return self.first,
+        };
+
+        let headers = self.headers;
+        headers.finish(&mut data, true);
+
+        let vnet = VirtioNetHdr {
+            flags: VIRTIO_NET_HDR_F_NEEDS_CSUM,
+            gso_type: match (headers.proto, headers.v4) {
+                (libc::IPPROTO_TCP, true) => GsoType::TcpV4,
+                (libc::IPPROTO_TCP, false) => GsoType::TcpV6,
+                _ => GsoType::UdpL4,
+            },
+            ecn: false,
+            hdr_len: headers.len() as u16,
+            gso_size: self.gso_size as u16,
+            csum_start: headers.ip_len as u16,
+            csum_offset: headers.csum_offset() as u16,
+        };
+
+        TunPacket::new(data.freeze()).with_vnet_hdr(vnet)
+    }
+}
+
+/// Fold the partial sum at `csum_start` into the checksum at `csum_start + csum_offset`
+fn complete_csum(data: &mut [u8], vnet: &VirtioNetHdr) -> io::Result<()> {
+    let start = vnet.csum_start as usize;
+    let at = start + vnet.csum_offset as usize;
+    if at + 2 > data.len() {
+        return Err(invalid("checksum out of the packet"));
+    }
+
+    let partial = u16::from_be_bytes([data[at], data[at + 1]]) as u64;
+    data[at..at + 2].fill(0);
+    let csum = !fold(checksum(&data[start..], partial));
+    data[at..at + 2].copy_from_slice(&csum.to_be_bytes());
+
+    Ok(())
+}
+
+pub(super) fn pseudo_header(pkt: &[u8], v4: bool, proto: u8, len: usize) -> u64 {
+    let addrs = if v4 { &pkt[12..20] } else { &pkt[8..40] };
+    checksum(addrs, proto as u64 + len as u64)
+}
+
+pub(super) fn checksum(data: &[u8], initial: u64) -> u64 {
+    let mut chunks = data.chunks_exact(2);
+    let mut sum = initial;
+    for chunk in &mut chunks {
+        sum += u16::from_be_bytes([chunk[0], chunk[1]]) as u64;
+    }
+    if let [byte] = chunks.remainder() {
+        sum += (*byte as u64) << 8;
+    }
+    sum
+}
+
+pub(super) fn fold(mut sum: u64) -> u16 {
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tcp_packet(id: u16, seq: u32, payload: &[u8], flags: u8) -> Vec<u8> {
+        let mut pkt = vec![0u8; 40];
+        pkt[0] = 0x45;
+        pkt[4..6].copy_from_slice(&id.to_be_bytes());
+        pkt[8] = 64;
+        pkt[9] = libc::IPPROTO_TCP as u8;
+        pkt[12..16].copy_from_slice(&[10, 0, 0, 1]);
+        pkt[16..20].copy_from_slice(&[10, 0, 0, 2]);
+        pkt[20..22].copy_from_slice(&1234u16.to_be_bytes());
+        pkt[22..24].copy_from_slice(&80u16.to_be_bytes());
+        pkt[24..28].copy_from_slice(&seq.to_be_bytes());
+        pkt[32] = 5 << 4;
+        pkt[33] = TCP_ACK | flags;
+        pkt[34..36].copy_from_slice(&1024u16.to_be_bytes());
+        pkt.extend_from_slice(payload);
+
+        let headers = Headers::parse(&pkt).unwrap();
+        headers.finish(&mut pkt, false);
+        pkt
+    }
+
+    fn valid(pkt: &[u8]) -> bool {
+        let l4_len = pkt.len() - 20;
+        fold(checksum(&pkt[..20], 0)) == 0xffff
+            && fold(checksum(
+                &pkt[20..],
+                pseudo_header(pkt, true, pkt[9], l4_len),
+            )) == 0xffff
+    }
+
+    #[test]
+    fn segment() {
+        let payload: Vec<u8> = (0..2500u32).map(|i| i as u8).collect();
+        let mut pkt = tcp_packet(7, 1000, &payload, TCP_PSH);
+        Headers::parse(&pkt).unwrap().finish(&mut pkt, true);
+
+        let vnet = VirtioNetHdr {
+            flags: VIRTIO_NET_HDR_F_NEEDS_CSUM,
+            gso_type: GsoType::TcpV4,
+            hdr_len: 40,
+            gso_size: 1000,
+            csum_start: 20,
+            csum_offset: 16,
+            ..Default::default()
+        };
+        let segments = gso_segment(TunPacket::new(pkt).with_vnet_hdr(vnet)).unwrap();
+
+        assert_eq!(3, segments.len());
+        for (i, seg) in segments.iter().enumerate() {
+            let seg = seg.get_bytes();
+            assert!(valid(seg));
+            assert_eq!(1000 + 1000 * i as u32, read_u32(&seg[24..]));
+            assert_eq!(i == 2, seg[33] & TCP_PSH != 0);
+        }
+        assert_eq!(540, segments[2].get_bytes().len());
+        assert_eq!(payload[1000], segments[1].get_bytes()[40]);
+    }
+
+    #[test]
+    fn segment_without_payload() {
+        let mut pkt = tcp_packet(7, 1000, &[], TCP_FIN);
+        Headers::parse(&pkt).unwrap().finish(&mut pkt, true);
+
+        let vnet = VirtioNetHdr {
+            flags: VIRTIO_NET_HDR_F_NEEDS_CSUM,
+            gso_type: GsoType::TcpV4,
+            hdr_len: 40,
+            gso_size: 1000,
+            csum_start: 20,
+            csum_offset: 16,
+            ..Default::default()
+        };
+        let segments = gso_segment(TunPacket::new(pkt).with_vnet_hdr(vnet)).unwrap();
+
+        assert_eq!(1, segments.len());
+        let seg = segments[0].get_bytes();
+        assert_eq!(40, seg.len());
+        assert!(valid(seg));
+        assert!(seg[33] & TCP_FIN != 0);
+    }
+
+    #[test]
+    fn coalesce() {
+        let payload: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        let pkts = vec![
+            TunPacket::new(tcp_packet(0, 1, &payload, 0)),
+            TunPacket::new(tcp_packet(1, 1001, &payload, 0)),
+            TunPacket::new(tcp_packet(2, 2001, &payload[..10], TCP_PSH)),
+            // a new run, the previous one ended with PSH
+            TunPacket::new(tcp_packet(3, 2011, &payload, 0)),
+            // out of order, not coalesced
+            TunPacket::new(tcp_packet(4, 9000, &payload, 0)),
+        ];
+        let originals: Vec<Vec<u8>> = pkts.iter().map(|p| p.get_bytes().to_vec()).collect();
+
+        let coalesced = gro_coalesce(pkts, false);
+        assert_eq!(3, coalesced.len());
+
+        let vnet = coalesced[0].vnet_hdr().unwrap();
+        assert_eq!(GsoType::TcpV4, vnet.gso_type);
+        assert_eq!(1000, vnet.gso_size);
+        assert_eq!(40 + 2010, coalesced[0].get_bytes().len());
+        assert!(coalesced[1].vnet_hdr().is_none());
+
+        let mut coalesced = coalesced.into_iter();
+        let segments = gso_segment(coalesced.next().unwrap()).unwrap();
+        let segments: Vec<Vec<u8>> = segments.iter().map(|p| p.get_bytes().to_vec()).collect();
+        assert_eq!(originals[..3], segments[..]);
+    }
+}
@@ -7,6 +7,12 @@ use tokio::io::{unix::AsyncFd, AsyncRead, AsyncWrite};
 use tokio_util::codec::Framed;
 
 use super::codec::TunPacketCodec;
+#[cfg(target_os = "linux")]
+use super::events::AsyncEvents;
+#[cfg(target_os = "linux")]
+use crate::tun::TunController;
+#[cfg(target_os = "linux")]
+use crate::{interface::Statistics, vnet::VirtioNetHdr};
 
 pub struct AsyncTun {
     inner: AsyncFd<Tun>,
@@ -16,9 +22,9 @@ impl AsyncTun {
     pub fn new(tun: Tun) -> Result<AsyncTun> {
         tun.set_nonblocking()?;
 
-        Ok(AsyncTun {
-            inner: AsyncFd::new(tun)?,
-        })
+        // SAFETY: the queue is owned by `tun` and only closed when it is dropped
+        let inner = unsafe { AsyncFd::register(tun) }.map_err(io::Error::from)?;
+        Ok(AsyncTun { inner })
     }
 
     pub fn new_multi_queue(tuns: Vec<Tun>) -> Result<Vec<AsyncTun>> {
@@ -37,8 +43,74 @@ impl AsyncTun {
         let pi = self.get_mut().has_packet_information();
         let codec = TunPacketCodec::new(pi, self.inner.get_ref().mtu().unwrap_or(1500 + 4));
 
+        #[cfg(target_os = "linux")]
+        let codec = codec.with_layer(self.get_ref().layer());
+
+        #[cfg(target_os = "linux")]
+        if let len @ 1.. = self.get_ref().vnet_hdr_len() {
+            // segmentation offloads hand over packets up to 64KB, the first read included
+            let codec = TunPacketCodec::new(pi, u16::MAX as i32)
+                .with_vnet_hdr(len)
+                .with_layer(self.get_ref().layer());
+            return Framed::with_capacity(self, codec, u16::MAX as usize + len + 4);
+        }
+
         Framed::new(self, codec)
     }
+
+    /// See [`Tun::open_queue`]
+    #[cfg(target_os = "linux")]
+    pub fn open_queue(&self) -> Result<AsyncTun> {
+        AsyncTun::new(self.get_ref().open_queue()?)
+    }
+
+    /// See [`Tun::enable_queue`]
+    #[cfg(target_os = "linux")]
+    pub fn enable_queue(&self, value: bool) -> Result<()> {
+        self.get_ref().enable_queue(value)
+    }
+
+    /// See [`Tun::controller`]
+    #[cfg(target_os = "linux")]
+    pub fn controller(&self) -> TunController {
+        self.get_ref().controller()
+    }
+
+    /// See [`Tun::counters`]
+    #[cfg(target_os = "linux")]
+    pub fn counters(&self) -> Statistics {
+        self.get_ref().counters()
+    }
+
+    /// See [`Tun::events`]
+    #[cfg(target_os = "linux")]
+    pub fn events(&self) -> Result<AsyncEvents> {
+        AsyncEvents::new(self.get_ref().events()?)
+    }
+
+    /// See [`Tun::read_vnet`]
+    #[cfg(target_os = "linux")]
+    pub async fn read_vnet(&mut self, buf: &mut [u8]) -> io::Result<(VirtioNetHdr, usize)> {
+        loop {
+            let mut guard = self.inner.readable_mut().await?;
+            match guard.try_io(|inner| inner.get_mut().read_vnet(buf)) {
+                Ok(res) => return res,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// See [`Tun::write_vnet`]
+    #[cfg(target_os = "linux")]
+    pub async fn write_vnet(&mut self, vnet: &VirtioNetHdr, buf: &[u8]) -> io::Result<usize> {
+        loop {
+            let mut guard = self.inner.writable_mut().await?;
+            match guard.try_io(|inner| inner.get_mut().write_vnet(vnet, buf)) {
+                Ok(res) => return res,
+                Err(_would_block) => continue,
+            }
+        }
+    }
 }
 
 impl AsyncRead for AsyncTun {
@@ -1,46 +1,140 @@
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 #[cfg(unix)]
 use std::os::unix::io::RawFd;
 #[cfg(windows)]
 use std::os::windows::raw::HANDLE;
 
-use crate::address::IntoIpv4Addr;
+use crate::address::{IntoIpAddr, IntoIpv4Addr};
 use crate::error::{Error, Result};
+use crate::ethernet::MacAddr;
+use crate::network::{IntoIpNetwork, IpNetwork};
+use crate::route::Route;
 use crate::tun::{Tun, TunConf};
 #[cfg(feature = "async")]
 use crate::AsyncTun;
 
+// 68 is the minimum MTU an IPv4 host must accept
+const MIN_MTU: i32 = 68;
+const MAX_MTU: i32 = 65535;
+// MAX_TAP_QUEUES of the linux tun driver
+#[cfg(target_os = "linux")]
+const MAX_QUEUES: usize = 256;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Layer {
+    #[cfg_attr(feature = "serde", serde(alias = "L2", alias = "tap"))]
     L2,
     #[default]
+    #[cfg_attr(feature = "serde", serde(alias = "L3", alias = "tun"))]
     L3,
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Configuration {
     pub(crate) name: Option<String>,
     pub(crate) platform: TunConf,
 
     pub(crate) address: Option<Ipv4Addr>,
+    #[cfg_attr(feature = "serde", serde(rename = "destination"))]
     pub(crate) destnation: Option<Ipv4Addr>,
     pub(crate) broadcast: Option<Ipv4Addr>,
     pub(crate) netmask: Option<Ipv4Addr>,
+    // IPv6 addresses with their prefix length
+    #[cfg_attr(feature = "serde", serde(with = "ipv6_networks"))]
+    pub(crate) ipv6: Vec<(Ipv6Addr, u8)>,
+    // additional addresses installed after the primary one
+    pub(crate) addresses: Vec<IpNetwork>,
+    // hardware address of an L2 device
+    pub(crate) mac_address: Option<MacAddr>,
     pub(crate) mtu: Option<i32>,
-    // Set the interface to be enabled once crated
-    pub(crate) enabled: bool,
+    pub(crate) routes: Vec<Route>,
+    // Set the interface to be enabled once crated, if not set a created device
+    // is disabled and an existing one is left as is
+    #[cfg_attr(feature = "serde", serde(rename = "up"))]
+    pub(crate) enabled: Option<bool>,
     pub(crate) layer: Layer,
+    // bridge the device is enslaved to once configured
+    pub(crate) bridge: Option<String>,
     pub(crate) queues: Option<usize>,
     #[cfg(unix)]
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) raw_fd: Option<RawFd>,
     #[cfg(not(unix))]
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) raw_fd: Option<i32>,
     #[cfg(windows)]
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) raw_handle: Option<HANDLE>,
+    // why the builder failed to convert a value, reported by `validate`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) errors: Vec<(&'static str, String)>,
+}
+
+/// IPv6 addresses are written in CIDR notation, e.g. `ipv6 = ["fd00::1/64"]`
+#[cfg(feature = "serde")]
+mod ipv6_networks {
+    use crate::network::IpNetwork;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::net::{IpAddr, Ipv6Addr};
+
+    pub fn serialize<S>(ipv6: &[(Ipv6Addr, u8)], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(ipv6.iter().map(|(addr, prefix)| format!("{addr}/{prefix}")))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<(Ipv6Addr, u8)>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<IpNetwork>::deserialize(deserializer)?
+            .into_iter()
+            .map(|network| match network.addr() {
+                IpAddr::V6(addr) => Ok((addr, network.prefix())),
+                IpAddr::V4(addr) => Err(de::Error::custom(format!("not an IPv6 address: {addr}"))),
+            })
+            .collect()
+    }
 }
 
 impl Configuration {
+    /// Load a configuration described in TOML, e.g.
+    ///
+    /// ```toml
+    /// name = "tun0"
+    /// address = "10.0.0.1"
+    /// netmask = "255.255.255.0"
+    /// ipv6 = ["fd00::1/64"]
+    /// layer = "tun"
+    /// up = true
+    ///
+    /// [[routes]]
+    /// destination = "10.1.0.0/16"
+    /// metric = 100
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    /// Load a configuration described in JSON, with the same fields as [`Configuration::from_toml_str`]
+    #[cfg(feature = "serde")]
+    pub fn from_json_str(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+
     pub fn platform<F: FnOnce(&mut TunConf)>(&mut self, f: F) -> &mut Self {
         f(&mut self.platform);
         self
@@ -52,22 +146,102 @@ impl Configuration {
     }
 
     pub fn address<A: IntoIpv4Addr>(&mut self, value: A) -> &mut Self {
-        self.address = Some(value.into_ipv4().unwrap());
+        if let Some(addr) = self.replace("address", value.into_ipv4()) {
+            self.address = Some(addr);
+        }
         self
     }
 
     pub fn destination<A: IntoIpv4Addr>(&mut self, value: A) -> &mut Self {
-        self.destnation = Some(value.into_ipv4().unwrap());
+        if let Some(addr) = self.replace("destination", value.into_ipv4()) {
+            self.destnation = Some(addr);
+        }
         self
     }
 
     pub fn broadcast<A: IntoIpv4Addr>(&mut self, value: A) -> &mut Self {
-        self.broadcast = Some(value.into_ipv4().unwrap());
+        if let Some(addr) = self.replace("broadcast", value.into_ipv4()) {
+            self.broadcast = Some(addr);
+        }
         self
     }
 
     pub fn netmask<A: IntoIpv4Addr>(&mut self, value: A) -> &mut Self {
-        self.netmask = Some(value.into_ipv4().unwrap());
+        if let Some(addr) = self.replace("netmask", value.into_ipv4()) {
+            self.netmask = Some(addr);
+        }
+        self
+    }
+
+    /// Set the address and its prefix at once from CIDR notation, e.g. `10.0.0.1/24`,
+    /// an IPv4 network replaces `address` and `netmask`, an IPv6 one is added
+    /// the way [`Configuration::ipv6_address`] does
+    pub fn network<A: IntoIpNetwork>(&mut self, value: A) -> &mut Self {
+        // a valid network overrides a failed address or netmask
+        let Some(network) = self.replace("network", value.into_network()) else {
+            return self;
+        };
+
+        match (network.addr(), network.netmask()) {
+            (IpAddr::V4(addr), IpAddr::V4(netmask)) => {
+                self.errors
+                    .retain(|(field, _)| *field != "address" && *field != "netmask");
+                self.address = Some(addr);
+                self.netmask = Some(netmask);
+            }
+            (IpAddr::V6(addr), _) => self.ipv6.push((addr, network.prefix())),
+            _ => unreachable!(),
+        }
+        self
+    }
+
+    pub fn ipv6_address<A: IntoIpAddr>(&mut self, value: A, prefix: u8) -> &mut Self {
+        if let Some(addr) = self.convert("ipv6_address", value.into_ipv6()) {
+            self.ipv6.push((addr, prefix));
+        }
+        self
+    }
+
+    /// Additional IPv4 or IPv6 addresses in CIDR notation, e.g. `["10.0.0.2/24", "fd00::2/64"]`
+    pub fn addresses<I>(&mut self, values: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: IntoIpNetwork,
+    {
+        for value in values {
+            if let Some(network) = self.convert("addresses", value.into_network()) {
+                self.addresses.push(network);
+            }
+        }
+        self
+    }
+
+    /// Like `convert` for a field set again, a previous failure is forgotten
+    fn replace<T>(&mut self, field: &'static str, res: Result<T>) -> Option<T> {
+        self.errors.retain(|(failed, _)| *failed != field);
+        self.convert(field, res)
+    }
+
+    /// Keep the converted value, or remember the failure to report it from `validate`
+    fn convert<T>(&mut self, field: &'static str, res: Result<T>) -> Option<T> {
+        match res {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.errors.push((field, err.to_string()));
+                None
+            }
+        }
+    }
+
+    /// The hardware address of an L2 device, e.g. `02:00:00:00:00:01`
+    pub fn mac_address<A>(&mut self, value: A) -> &mut Self
+    where
+        A: TryInto<MacAddr>,
+    {
+        let res = value.try_into().map_err(|_| Error::InvalidAddress);
+        if let Some(addr) = self.replace("mac_address", res) {
+            self.mac_address = Some(addr);
+        }
         self
     }
 
@@ -76,13 +250,20 @@ impl Configuration {
         self
     }
 
+    /// Install a route through the device once it is configured,
+    /// the route is removed again when the device is dropped
+    pub fn route(&mut self, route: Route) -> &mut Self {
+        self.routes.push(route);
+        self
+    }
+
     pub fn up(&mut self) -> &mut Self {
-        self.enabled = true;
+        self.enabled = Some(true);
         self
     }
 
     pub fn down(&mut self) -> &mut Self {
-        self.enabled = false;
+        self.enabled = Some(false);
         self
     }
 
@@ -91,11 +272,20 @@ impl Configuration {
         self
     }
 
+    /// Enslave the device to an existing bridge once it is configured, L2 only
+    pub fn bridge<S: AsRef<str>>(&mut self, name: S) -> &mut Self {
+        self.bridge = Some(name.as_ref().into());
+        self
+    }
+
     pub fn queues(&mut self, queues: usize) -> &mut Self {
         self.queues = Some(queues);
         self
     }
 
+    /// Use an already opened device instead of creating one, the descriptor is
+    /// owned by the device afterwards. On Linux it must be an attached
+    /// `/dev/net/tun` queue, its name and flags are queried from the kernel
     #[cfg(unix)]
     pub fn raw_fd(&mut self, fd: RawFd) -> &mut Self {
         self.raw_fd = Some(fd);
@@ -112,7 +302,98 @@ impl Configuration {
         self
     }
 
+    /// Whether an existing device is opened rather than created
+    pub(crate) fn opens_existing(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        if self.platform.attach {
+            return true;
+        }
+        #[cfg(windows)]
+        if self.raw_handle.is_some() {
+            return true;
+        }
+        self.raw_fd.is_some()
+    }
+
+    /// Check the whole configuration without creating a device,
+    /// the `build*` methods call it before anything else
+    pub fn validate(&self) -> Result<()> {
+        if let Some((field, value)) = self.errors.first() {
+            return Err(Error::InvalidValue {
+                field,
+                value: value.clone(),
+            });
+        }
+
+        if let Some(name) = self.name.as_ref() {
+            validate_name(name)?;
+        }
+
+        if let Some(bridge) = self.bridge.as_ref() {
+            validate_name(bridge)?;
+            #[cfg(not(target_os = "linux"))]
+            return Err(Error::NotImplemented);
+            #[cfg(target_os = "linux")]
+            if self.layer != Layer::L2 {
+                return Err(Error::UnsupportedLayer);
+            }
+        }
+
+        if let Some(mtu) = self.mtu {
+            if !(MIN_MTU..=MAX_MTU).contains(&mtu) {
+                return Err(Error::InvalidValue {
+                    field: "mtu",
+                    value: mtu.to_string(),
+                });
+            }
+        }
+
+        if let Some(addr) = self.mac_address {
+            if self.layer != Layer::L2 {
+                return Err(Error::UnsupportedLayer);
+            }
+            if addr.is_multicast() || addr.is_unspecified() {
+                return Err(Error::InvalidValue {
+                    field: "mac_address",
+                    value: addr.to_string(),
+                });
+            }
+        }
+
+        if let Some(netmask) = self.netmask {
+            IpNetwork::from_netmask(Ipv4Addr::UNSPECIFIED, netmask)?;
+        }
+
+        for (addr, prefix) in self.ipv6.iter() {
+            IpNetwork::new(*addr, *prefix)?;
+        }
+
+        for route in self.routes.iter() {
+            IpNetwork::new(route.destination, route.prefix)?;
+        }
+
+        match self.queues {
+            Some(0) => return Err(Error::InvalidQueuesNumber),
+            // an inherited descriptor is a single queue
+            Some(n) if n > 1 && self.raw_fd.is_some() => return Err(Error::InvalidQueuesNumber),
+            #[cfg(target_os = "linux")]
+            Some(n) if n > MAX_QUEUES => return Err(Error::InvalidQueuesNumber),
+            #[cfg(not(target_os = "linux"))]
+            Some(n) if n > 1 => return Err(Error::InvalidQueuesNumber),
+            _ => {}
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        if self.layer == Layer::L2 {
+            return Err(Error::UnsupportedLayer);
+        }
+
+        Ok(())
+    }
+
     pub fn build(&self) -> Result<Tun> {
+        self.validate()?;
+
         match self.queues {
             Some(n) if n > 1 => Err(Error::InvalidConfig),
             _ => Tun::new(self),
@@ -121,17 +402,181 @@ impl Configuration {
 
     #[cfg(target_os = "linux")]
     pub fn build_multi_queue(&self) -> Result<Vec<Tun>> {
+        self.validate()?;
+
         Tun::new_multi_queue(self)
     }
 
     #[cfg(feature = "async")]
     pub fn build_async(&self) -> Result<AsyncTun> {
-        AsyncTun::new(Tun::new(self)?)
+        self.validate()?;
+
+        match self.queues {
+            Some(n) if n > 1 => Err(Error::InvalidConfig),
+            _ => AsyncTun::new(Tun::new(self)?),
+        }
     }
 
     #[cfg(feature = "async")]
     #[cfg(target_os = "linux")]
     pub fn build_async_multi_queue(&self) -> Result<Vec<AsyncTun>> {
+        self.validate()?;
+
         AsyncTun::new_multi_queue(Tun::new_multi_queue(self)?)
     }
 }
+
+/// Check an interface name the way the kernel does
+pub(crate) fn validate_name(name: &str) -> Result<()> {
+    // the name must fit IFNAMSIZ with its trailing nul
+    #[cfg(unix)]
+    if name.len() >= libc::IFNAMSIZ {
+        return Err(Error::NameTooLong);
+    }
+    if name.is_empty()
+        || name
+            .chars()
+            .any(|c| c == '/' || c == ':' || c.is_whitespace() || c == '\0')
+    {
+        return Err(Error::InvalidName);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::Configuration;
+    use crate::error::Error;
+
+    #[test]
+    fn validate() {
+        let mut config = Configuration::default();
+        config.address("10.0.0.300").netmask("255.255.255.0");
+        match config.validate() {
+            Err(Error::InvalidValue { field, value }) => {
+                assert_eq!("address", field);
+                assert_eq!("invalid address", value);
+            }
+            res => panic!("unexpected {res:?}"),
+        }
+        assert!(config.build().is_err());
+        // set again, the first failure is forgotten
+        config.address("10.0.0.1");
+        assert!(config.validate().is_ok());
+        config.address("10.0.0.300").network("10.0.0.1/24");
+        assert!(config.validate().is_ok());
+        config.network("10.0.0.1/40");
+        match config.validate() {
+            Err(Error::InvalidValue { field, value }) => {
+                assert_eq!("network", field);
+                assert_eq!("invalid prefix length 40", value);
+            }
+            res => panic!("unexpected {res:?}"),
+        }
+
+        let mut config = Configuration::default();
+        config.name("a-very-long-tun-name");
+        assert!(matches!(config.validate(), Err(Error::NameTooLong)));
+
+        let mut config = Configuration::default();
+        config.name("tun 0");
+        assert!(matches!(config.validate(), Err(Error::InvalidName)));
+
+        let mut config = Configuration::default();
+        config.mtu(20);
+        assert!(matches!(
+            config.validate(),
+            Err(Error::InvalidValue { field: "mtu", .. })
+        ));
+
+        let mut config = Configuration::default();
+        config.netmask("255.0.255.0");
+        assert!(matches!(config.validate(), Err(Error::InvalidNetmask(_))));
+
+        let mut config = Configuration::default();
+        config.ipv6_address("fd00::1", 129);
+        assert!(matches!(config.validate(), Err(Error::InvalidPrefix(129))));
+
+        let mut config = Configuration::default();
+        config.mac_address("02:00:00:00:00:01");
+        assert!(matches!(config.validate(), Err(Error::UnsupportedLayer)));
+        config.layer(super::Layer::L2);
+        assert!(config.validate().is_ok());
+        config.mac_address("01:00:5e:00:00:01");
+        assert!(matches!(
+            config.validate(),
+            Err(Error::InvalidValue {
+                field: "mac_address",
+                ..
+            })
+        ));
+
+        let mut config = Configuration::default();
+        config.layer(super::Layer::L2).bridge("br 0");
+        assert!(matches!(config.validate(), Err(Error::InvalidName)));
+
+        let mut config = Configuration::default();
+        config.queues(0);
+        assert!(matches!(config.validate(), Err(Error::InvalidQueuesNumber)));
+
+        let mut config = Configuration::default();
+        config
+            .name("tun0")
+            .network("10.0.0.1/24")
+            .addresses(["10.0.0.2/24", "fd00::1/64"])
+            .mtu(1400);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn from_toml() {
+        use super::Layer;
+        use crate::route::Route;
+        use std::net::Ipv4Addr;
+
+        let config = Configuration::from_toml_str(
+            r#"
+            name = "tun0"
+            address = "10.0.0.1"
+            netmask = "255.255.255.0"
+            ipv6 = ["fd00::1/64"]
+            addresses = ["10.0.0.2/24"]
+            mtu = 1400
+            layer = "tun"
+            up = true
+
+            [[routes]]
+            destination = "10.1.0.0/16"
+            metric = 100
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(Some("tun0"), config.name.as_deref());
+        assert_eq!(Some(Ipv4Addr::new(10, 0, 0, 1)), config.address);
+        assert_eq!(vec![("fd00::1".parse().unwrap(), 64)], config.ipv6);
+        assert_eq!(Layer::L3, config.layer);
+        assert_eq!(Some(true), config.enabled);
+        assert_eq!(
+            vec![Route::new(Ipv4Addr::new(10, 1, 0, 0), 16).metric(100)],
+            config.routes
+        );
+        assert!(config.validate().is_ok());
+
+        let json = serde_json::to_string(&config).unwrap();
+        let config = Configuration::from_json_str(&json).unwrap();
+        assert_eq!(Some(1400), config.mtu);
+        assert_eq!(1, config.addresses.len());
+
+        assert!(Configuration::from_json_str(r#"{ "ipv6": ["10.0.0.1/24"] }"#).is_err());
+
+        // an invalid route is kept as is, not turned into another one
+        let mut config = Configuration::default();
+        config.route(Route::new(Ipv4Addr::new(10, 1, 0, 0), 40));
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains(r#""destination":"10.1.0.0/40""#));
+        assert!(Configuration::from_json_str(&json).is_err());
+    }
+}
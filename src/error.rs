@@ -1,6 +1,7 @@
-use std::{ffi, io, num};
+use std::{ffi, io, net::IpAddr, num};
 
 #[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("invalid configuration")]
     InvalidConfig,
@@ -17,6 +18,15 @@ pub enum Error {
     #[error("invalid address")]
     InvalidAddress,
 
+    #[error("invalid {field}: {value}")]
+    InvalidValue { field: &'static str, value: String },
+
+    #[error("invalid prefix length {0}")]
+    InvalidPrefix(u8),
+
+    #[error("non-contiguous netmask {0}")]
+    InvalidNetmask(IpAddr),
+
     #[error("unsuported network layer of operation")]
     UnsupportedLayer,
 
@@ -38,6 +48,14 @@ pub enum Error {
     #[error(transparent)]
     ParseNum(#[from] num::ParseIntError),
 
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
     #[cfg(target_os = "windows")]
     #[error(transparent)]
     WintunError(#[from] wintun::Error),
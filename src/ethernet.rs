@@ -0,0 +1,246 @@
+use std::{fmt, str::FromStr};
+
+use crate::error::{Error, Result};
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+pub const ETHERTYPE_IPV6: u16 = 0x86dd;
+/// 802.1Q tag
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
+/// 802.1ad outer tag of a double tagged frame
+pub const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+/// A hardware address, written `02:00:00:00:00:01`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl MacAddr {
+    pub const BROADCAST: MacAddr = MacAddr([0xff; 6]);
+
+    pub const fn new(a: u8, b: u8, c: u8, d: u8, e: u8, f: u8) -> Self {
+        Self([a, b, c, d, e, f])
+    }
+
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+
+    /// Broadcast included
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 1 != 0
+    }
+
+    pub fn is_unspecified(&self) -> bool {
+        self.0 == [0; 6]
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl FromStr for MacAddr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut octets = [0u8; 6];
+        let mut parts = s.split([':', '-']);
+        for octet in octets.iter_mut() {
+            *octet = parts
+                .next()
+                .filter(|part| part.len() == 2)
+                .and_then(|part| u8::from_str_radix(part, 16).ok())
+                .ok_or(Error::InvalidAddress)?;
+        }
+
+        match parts.next() {
+            Some(_) => Err(Error::InvalidAddress),
+            None => Ok(Self(octets)),
+        }
+    }
+}
+
+impl From<[u8; 6]> for MacAddr {
+    fn from(octets: [u8; 6]) -> Self {
+        Self(octets)
+    }
+}
+
+impl TryFrom<&str> for MacAddr {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl TryFrom<String> for MacAddr {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<MacAddr> for String {
+    fn from(addr: MacAddr) -> Self {
+        addr.to_string()
+    }
+}
+
+/// A VLAN tag between the source address and the EtherType
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VlanTag {
+    /// [`ETHERTYPE_VLAN`] or [`ETHERTYPE_QINQ`]
+    pub tpid: u16,
+    /// Priority code point
+    pub pcp: u8,
+    /// Drop eligible indicator
+    pub dei: bool,
+    pub vid: u16,
+}
+
+impl VlanTag {
+    pub fn new(vid: u16) -> Self {
+        Self {
+            tpid: ETHERTYPE_VLAN,
+            pcp: 0,
+            dei: false,
+            vid,
+        }
+    }
+
+    fn tci(&self) -> u16 {
+        (self.pcp as u16) << 13 | (self.dei as u16) << 12 | self.vid & 0x0fff
+    }
+}
+
+/// The header of a frame read from or written to a TAP device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthernetHeader {
+    pub destination: MacAddr,
+    pub source: MacAddr,
+    /// Outermost first
+    pub vlans: Vec<VlanTag>,
+    /// The type of the payload, after the VLAN tags
+    pub ether_type: u16,
+}
+
+impl EthernetHeader {
+    pub const MIN_LEN: usize = 14;
+
+    pub fn new(destination: MacAddr, source: MacAddr, ether_type: u16) -> Self {
+        Self {
+            destination,
+            source,
+            vlans: Vec::new(),
+            ether_type,
+        }
+    }
+
+    pub fn parse(frame: &[u8]) -> Option<Self> {
+        if frame.len() < Self::MIN_LEN {
+            return None;
+        }
+
+        let mut header = Self::new(
+            MacAddr(frame[0..6].try_into().unwrap()),
+            MacAddr(frame[6..12].try_into().unwrap()),
+            u16::from_be_bytes([frame[12], frame[13]]),
+        );
+
+        let mut offset = 12;
+        while let ETHERTYPE_VLAN | ETHERTYPE_QINQ = header.ether_type {
+            let tag = frame.get(offset..offset + 6)?;
+            let tci = u16::from_be_bytes([tag[2], tag[3]]);
+            header.vlans.push(VlanTag {
+                tpid: header.ether_type,
+                pcp: (tci >> 13) as u8,
+                dei: tci & 0x1000 != 0,
+                vid: tci & 0x0fff,
+            });
+            header.ether_type = u16::from_be_bytes([tag[4], tag[5]]);
+            offset += 4;
+        }
+
+        Some(header)
+    }
+
+    /// Length of the header on the wire, where the payload starts
+    pub fn size(&self) -> usize {
+        Self::MIN_LEN + 4 * self.vlans.len()
+    }
+
+    /// Append the header to `buf`, to be followed by the payload
+    pub fn write(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.destination.0);
+        buf.extend_from_slice(&self.source.0);
+        for vlan in self.vlans.iter() {
+            buf.extend_from_slice(&vlan.tpid.to_be_bytes());
+            buf.extend_from_slice(&vlan.tci().to_be_bytes());
+        }
+        buf.extend_from_slice(&self.ether_type.to_be_bytes());
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.size());
+        self.write(&mut buf);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EthernetHeader, MacAddr, VlanTag, ETHERTYPE_IPV6, ETHERTYPE_QINQ};
+
+    #[test]
+    fn mac_addr() {
+        let addr: MacAddr = "02:00:5e:10:00:0a".parse().unwrap();
+        assert_eq!(MacAddr::new(2, 0, 0x5e, 0x10, 0, 0x0a), addr);
+        assert_eq!("02:00:5e:10:00:0a", addr.to_string());
+        assert_eq!(Some(addr), "02-00-5E-10-00-0A".parse().ok());
+        assert!(!addr.is_multicast() && MacAddr::BROADCAST.is_multicast());
+
+        assert!("02:00:5e:10:00".parse::<MacAddr>().is_err());
+        assert!("02:00:5e:10:00:0a:01".parse::<MacAddr>().is_err());
+        assert!("2:00:5e:10:00:0a".parse::<MacAddr>().is_err());
+    }
+
+    #[test]
+    fn vlan_header() {
+        let mut header = EthernetHeader::new(
+            MacAddr::BROADCAST,
+            MacAddr::new(2, 0, 0, 0, 0, 1),
+            ETHERTYPE_IPV6,
+        );
+        header.vlans.push(VlanTag {
+            tpid: ETHERTYPE_QINQ,
+            ..VlanTag::new(100)
+        });
+        header.vlans.push(VlanTag {
+            pcp: 5,
+            ..VlanTag::new(42)
+        });
+
+        let mut frame = header.to_bytes();
+        assert_eq!(22, frame.len());
+        assert_eq!([0x88, 0xa8, 0x00, 0x64], frame[12..16]);
+        assert_eq!([0x81, 0x00, 0xa0, 0x2a], frame[16..20]);
+
+        frame.extend_from_slice(&[0x60, 0, 0, 0]);
+        assert_eq!(Some(header), EthernetHeader::parse(&frame));
+        assert_eq!(None, EthernetHeader::parse(&frame[..18]));
+    }
+}
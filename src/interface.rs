@@ -1,6 +1,69 @@
 use crate::configuration::Configuration;
 use crate::error::*;
+use crate::ethernet::MacAddr;
+use crate::network::IpNetwork;
 use std::net::Ipv4Addr;
+use std::ops::Sub;
+
+bitflags::bitflags! {
+    /// The `IFF_*` flags of an interface, some of them only reflect its state
+    /// and are left as is when set, e.g. `RUNNING`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct InterfaceFlags: u32 {
+        const UP = libc::IFF_UP as u32;
+        const BROADCAST = libc::IFF_BROADCAST as u32;
+        const DEBUG = libc::IFF_DEBUG as u32;
+        const LOOPBACK = libc::IFF_LOOPBACK as u32;
+        const POINTOPOINT = libc::IFF_POINTOPOINT as u32;
+        const RUNNING = libc::IFF_RUNNING as u32;
+        const NOARP = libc::IFF_NOARP as u32;
+        const PROMISC = libc::IFF_PROMISC as u32;
+        const ALLMULTI = libc::IFF_ALLMULTI as u32;
+        const MULTICAST = libc::IFF_MULTICAST as u32;
+        #[cfg(target_os = "linux")]
+        const LOWER_UP = libc::IFF_LOWER_UP as u32;
+        #[cfg(target_os = "linux")]
+        const DORMANT = libc::IFF_DORMANT as u32;
+
+        // flags without a name are kept as is
+        const _ = !0;
+    }
+}
+
+/// A snapshot of the counters of an interface, subtract an earlier snapshot
+/// for what happened in between, e.g. to compute rates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statistics {
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+    pub multicast: u64,
+}
+
+impl Sub for Statistics {
+    type Output = Statistics;
+
+    /// Counters wrap around rather than overflow, e.g. the 32 bits ones of some drivers
+    fn sub(self, earlier: Self) -> Self::Output {
+        Statistics {
+            rx_packets: self.rx_packets.wrapping_sub(earlier.rx_packets),
+            tx_packets: self.tx_packets.wrapping_sub(earlier.tx_packets),
+            rx_bytes: self.rx_bytes.wrapping_sub(earlier.rx_bytes),
+            tx_bytes: self.tx_bytes.wrapping_sub(earlier.tx_bytes),
+            rx_errors: self.rx_errors.wrapping_sub(earlier.rx_errors),
+            tx_errors: self.tx_errors.wrapping_sub(earlier.tx_errors),
+            rx_dropped: self.rx_dropped.wrapping_sub(earlier.rx_dropped),
+            tx_dropped: self.tx_dropped.wrapping_sub(earlier.tx_dropped),
+            multicast: self.multicast.wrapping_sub(earlier.multicast),
+        }
+    }
+}
 
 pub trait Interface /*: Read + Write*/ {
     // type Queue: Read + Write;
@@ -23,11 +86,20 @@ pub trait Interface /*: Read + Write*/ {
             self.set_netmask(addr)?;
         }
 
+        if let Some(mac) = config.mac_address {
+            self.set_mac_address(mac)?;
+        }
+
         if let Some(mtu) = config.mtu {
             self.set_mtu(mtu)?;
         }
 
-        self.enable(config.enabled)?;
+        // a created device is brought down unless `up` is set, an existing one is left as is
+        match config.enabled {
+            Some(enabled) => self.enable(enabled)?,
+            None if !config.opens_existing() => self.enable(false)?,
+            None => {}
+        }
 
         Ok(())
     }
@@ -35,9 +107,20 @@ pub trait Interface /*: Read + Write*/ {
     fn name(&self) -> Result<String>;
     fn set_name(&mut self, name: &str) -> Result<()>;
 
-    fn enable(&mut self, value: bool) -> Result<()>;
-    fn flags(&self, flags: Option<i16>) -> Result<i16>;
-    // fn set_enabled(&mut self, value: bool) -> Result<()>;
+    fn enable(&mut self, value: bool) -> Result<()> {
+        let mut flags = self.flags()?;
+
+        if value {
+            flags |= InterfaceFlags::UP | InterfaceFlags::RUNNING;
+        } else {
+            flags.remove(InterfaceFlags::UP);
+        }
+
+        self.set_flags(flags)
+    }
+
+    fn flags(&self) -> Result<InterfaceFlags>;
+    fn set_flags(&mut self, flags: InterfaceFlags) -> Result<()>;
 
     fn address(&self) -> Result<Ipv4Addr>;
     fn set_address(&mut self, addr: Ipv4Addr) -> Result<()>;
@@ -51,8 +134,26 @@ pub trait Interface /*: Read + Write*/ {
     fn netmask(&self) -> Result<Ipv4Addr>;
     fn set_netmask(&mut self, addr: Ipv4Addr) -> Result<()>;
 
+    /// The primary IPv4 address together with the prefix length of its netmask
+    fn network(&self) -> Result<IpNetwork> {
+        IpNetwork::from_netmask(self.address()?, self.netmask()?)
+    }
+
+    /// The hardware address of an L2 device
+    fn mac_address(&self) -> Result<MacAddr> {
+        Err(Error::UnsupportedLayer)
+    }
+    fn set_mac_address(&mut self, _addr: MacAddr) -> Result<()> {
+        Err(Error::UnsupportedLayer)
+    }
+
     fn mtu(&self) -> Result<i32>;
     fn set_mtu(&mut self, mtu: i32) -> Result<()>;
 
+    /// The counters kept by the kernel for the interface
+    fn statistics(&self) -> Result<Statistics> {
+        Err(Error::NotImplemented)
+    }
+
     fn queue(&mut self) -> &mut Self::Queue;
 }
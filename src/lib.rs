@@ -1,7 +1,14 @@
 mod configuration;
-pub use configuration::Configuration;
+pub use configuration::{Configuration, Layer};
 
 mod address;
+mod network;
+pub use network::IpNetwork;
+mod route;
+pub use route::Route;
+pub mod ethernet;
+pub mod vnet;
+
 mod error;
 pub mod interface;
 
@@ -20,8 +27,15 @@ pub use platform::tun;
 ))]
 mod r#async {
     pub mod codec;
+    pub mod dhcp;
+    #[cfg(target_os = "linux")]
+    pub mod events;
+    pub mod neighbor;
+    pub mod offload;
     pub mod tun;
 }
+#[cfg(all(feature = "async", target_os = "linux"))]
+pub use r#async::events::AsyncEvents;
 #[cfg(all(
     feature = "async",
     any(
@@ -33,9 +47,13 @@ mod r#async {
     )
 ))]
 pub use r#async::{
-    codec::TunPacket, 
-    codec::TunPacketCodec, 
-    tun::AsyncTun,
-    codec::PacketProtocol,
     codec::infer_proto,
+    codec::PacketProtocol,
+    codec::TunPacket,
+    codec::TunPacketCodec,
+    dhcp::{DhcpServer, Lease},
+    neighbor::NeighborResponder,
+    offload::gro_coalesce,
+    offload::gso_segment,
+    tun::AsyncTun,
 };
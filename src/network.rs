@@ -0,0 +1,285 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use crate::error::{Error, Result};
+
+/// An interface address together with the prefix length of its network, e.g. `10.0.0.1/24`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn new<A: Into<IpAddr>>(addr: A, prefix: u8) -> Result<Self> {
+        let addr = addr.into();
+        if prefix > max_prefix(&addr) {
+            return Err(Error::InvalidPrefix(prefix));
+        }
+
+        Ok(Self { addr, prefix })
+    }
+
+    /// Build from an address and a netmask such as `255.255.255.0`,
+    /// the netmask must be of the same family and contiguous
+    pub fn from_netmask<A: Into<IpAddr>, M: Into<IpAddr>>(addr: A, netmask: M) -> Result<Self> {
+        let addr = addr.into();
+        let netmask = netmask.into();
+        let prefix = match (addr, netmask) {
+            (IpAddr::V4(_), IpAddr::V4(mask)) => {
+                let mask = u32::from(mask);
+                (mask.leading_ones() + mask.trailing_zeros() == 32).then_some(mask.leading_ones())
+            }
+            (IpAddr::V6(_), IpAddr::V6(mask)) => {
+                let mask = u128::from(mask);
+                (mask.leading_ones() + mask.trailing_zeros() == 128).then_some(mask.leading_ones())
+            }
+            _ => None,
+        };
+
+        match prefix {
+            Some(prefix) => Self::new(addr, prefix as u8),
+            None => Err(Error::InvalidNetmask(netmask)),
+        }
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn netmask(&self) -> IpAddr {
+        match self.addr {
+            IpAddr::V4(_) => IpAddr::V4(
+                u32::MAX
+                    .checked_shl(32 - self.prefix as u32)
+                    .unwrap_or(0)
+                    .into(),
+            ),
+            IpAddr::V6(_) => IpAddr::V6(
+                u128::MAX
+                    .checked_shl(128 - self.prefix as u32)
+                    .unwrap_or(0)
+                    .into(),
+            ),
+        }
+    }
+
+    /// The network address, i.e. the address with its host bits cleared
+    pub fn network(&self) -> IpAddr {
+        match (self.addr, self.netmask()) {
+            (IpAddr::V4(addr), IpAddr::V4(mask)) => {
+                IpAddr::V4((u32::from(addr) & u32::from(mask)).into())
+            }
+            (IpAddr::V6(addr), IpAddr::V6(mask)) => {
+                IpAddr::V6((u128::from(addr) & u128::from(mask)).into())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn contains<A: Into<IpAddr>>(&self, addr: A) -> bool {
+        let other = Self {
+            addr: addr.into(),
+            prefix: self.prefix,
+        };
+        self.addr.is_ipv4() == other.addr.is_ipv4() && self.network() == other.network()
+    }
+
+    pub fn is_ipv4(&self) -> bool {
+        self.addr.is_ipv4()
+    }
+
+    pub fn is_ipv6(&self) -> bool {
+        self.addr.is_ipv6()
+    }
+}
+
+fn max_prefix(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = Error;
+
+    /// Parse `addr/prefix`, a bare address gets a host prefix (/32 or /128)
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once('/') {
+            Some((addr, prefix)) => {
+                let addr: IpAddr = addr.parse().map_err(|_| Error::InvalidAddress)?;
+                // a prefix which is not even a u8 is as invalid as the largest one
+                let prefix = prefix.parse().map_err(|_| Error::InvalidPrefix(u8::MAX))?;
+                Self::new(addr, prefix)
+            }
+            None => {
+                let addr: IpAddr = s.parse().map_err(|_| Error::InvalidAddress)?;
+                Self::new(addr, max_prefix(&addr))
+            }
+        }
+    }
+}
+
+impl From<IpAddr> for IpNetwork {
+    fn from(addr: IpAddr) -> Self {
+        Self {
+            addr,
+            prefix: max_prefix(&addr),
+        }
+    }
+}
+
+impl From<Ipv4Addr> for IpNetwork {
+    fn from(addr: Ipv4Addr) -> Self {
+        IpAddr::V4(addr).into()
+    }
+}
+
+impl From<Ipv6Addr> for IpNetwork {
+    fn from(addr: Ipv6Addr) -> Self {
+        IpAddr::V6(addr).into()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for IpNetwork {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for IpNetwork {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[allow(clippy::wrong_self_convention)]
+pub trait IntoIpNetwork {
+    fn into_network(&self) -> Result<IpNetwork>;
+}
+
+impl IntoIpNetwork for IpNetwork {
+    fn into_network(&self) -> Result<IpNetwork> {
+        Ok(*self)
+    }
+}
+
+impl IntoIpNetwork for &IpNetwork {
+    fn into_network(&self) -> Result<IpNetwork> {
+        Ok(**self)
+    }
+}
+
+impl IntoIpNetwork for str {
+    fn into_network(&self) -> Result<IpNetwork> {
+        self.parse()
+    }
+}
+
+impl IntoIpNetwork for &str {
+    fn into_network(&self) -> Result<IpNetwork> {
+        (*self).into_network()
+    }
+}
+
+impl IntoIpNetwork for String {
+    fn into_network(&self) -> Result<IpNetwork> {
+        self.as_str().into_network()
+    }
+}
+
+impl IntoIpNetwork for &String {
+    fn into_network(&self) -> Result<IpNetwork> {
+        (**self).into_network()
+    }
+}
+
+impl<A: Into<IpAddr> + Copy> IntoIpNetwork for (A, u8) {
+    fn into_network(&self) -> Result<IpNetwork> {
+        IpNetwork::new(self.0, self.1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IpNetwork;
+    use crate::error::Error;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn parse() {
+        let net: IpNetwork = "10.0.0.1/24".parse().unwrap();
+        assert_eq!(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), net.addr());
+        assert_eq!(24, net.prefix());
+        assert_eq!("10.0.0.1/24", net.to_string());
+
+        assert_eq!(128, "fd00::1".parse::<IpNetwork>().unwrap().prefix());
+        assert!(matches!(
+            "10.0.0.1/33".parse::<IpNetwork>(),
+            Err(Error::InvalidPrefix(33))
+        ));
+        assert!(matches!(
+            "10.0.0.1/300".parse::<IpNetwork>(),
+            Err(Error::InvalidPrefix(_))
+        ));
+        assert!(matches!(
+            "10.0.0.1/x".parse::<IpNetwork>(),
+            Err(Error::InvalidPrefix(_))
+        ));
+        assert!("fd00::1/129".parse::<IpNetwork>().is_err());
+        assert!("10.0.0/24".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn netmask() {
+        let net =
+            IpNetwork::from_netmask(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(255, 255, 255, 0))
+                .unwrap();
+        assert_eq!(24, net.prefix());
+        assert_eq!(IpAddr::V4(Ipv4Addr::new(255, 255, 255, 0)), net.netmask());
+        assert_eq!(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), net.network());
+        assert!(net.contains(Ipv4Addr::new(10, 0, 0, 200)));
+        assert!(!net.contains(Ipv4Addr::new(10, 0, 1, 1)));
+
+        assert!(matches!(
+            IpNetwork::from_netmask(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(255, 0, 255, 0)),
+            Err(Error::InvalidNetmask(_))
+        ));
+        assert!(matches!(
+            IpNetwork::new(Ipv4Addr::new(10, 0, 0, 1), 40),
+            Err(Error::InvalidPrefix(40))
+        ));
+
+        let net: IpNetwork = "fd00::1/64".parse().unwrap();
+        assert_eq!(
+            "ffff:ffff:ffff:ffff::".parse::<IpAddr>().unwrap(),
+            net.netmask()
+        );
+        assert_eq!(
+            0,
+            IpNetwork::new(Ipv4Addr::UNSPECIFIED, 0).unwrap().prefix()
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use super::netlink::Netlink;
+use crate::configuration::validate_name;
+use crate::error::{Error, Result};
+
+/// Create a bridge TAP devices can be enslaved to, see [`Tun::enslave`](super::tun::Tun::enslave)
+pub fn create_bridge(name: &str) -> Result<()> {
+    validate_name(name)?;
+
+    Netlink::new()?.new_link(name, "bridge")
+}
+
+/// Delete a bridge, its ports are released rather than deleted
+pub fn delete_bridge(name: &str) -> Result<()> {
+    let mut nl = Netlink::new()?;
+    let link = nl.link_by_name(name)?;
+    if link.kind.as_deref() != Some("bridge") {
+        return Err(Error::InvalidName);
+    }
+
+    nl.del_link(link.index)
+}
@@ -0,0 +1,90 @@
+use std::fs;
+
+use super::netlink::{Link, Netlink};
+use crate::configuration::Layer;
+use crate::error::{Error, Result};
+use crate::interface::InterfaceFlags;
+
+/// A TUN or TAP device of the host, whoever created it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub index: i32,
+    pub layer: Layer,
+    pub flags: InterfaceFlags,
+    pub multi_queue: bool,
+    pub packet_information: bool,
+    pub vnet_hdr: bool,
+    /// The uid allowed to attach to the device without CAP_NET_ADMIN
+    pub owner: Option<u32>,
+    /// The gid allowed to attach to the device without CAP_NET_ADMIN
+    pub group: Option<u32>,
+    /// Kept once its last queue is closed, e.g. left behind by a process which died
+    pub persistent: bool,
+}
+
+impl DeviceInfo {
+    /// Complete `link` with what the tun driver exposes in sysfs,
+    /// ENOENT when the device is gone
+    fn read(link: Link) -> Result<Self> {
+        let sysfs = |attr: &str| {
+            fs::read_to_string(format!("/sys/class/net/{}/{attr}", link.name))
+                .map(|value| value.trim().to_string())
+        };
+
+        let tun_flags = i32::from_str_radix(sysfs("tun_flags")?.trim_start_matches("0x"), 16)?;
+        // -1 when unset
+        let owner = sysfs("owner")?.parse().ok();
+        let group = sysfs("group")?.parse().ok();
+
+        Ok(Self {
+            name: link.name,
+            index: link.index,
+            layer: if tun_flags & libc::IFF_TAP != 0 {
+                Layer::L2
+            } else {
+                Layer::L3
+            },
+            flags: InterfaceFlags::from_bits_retain(link.flags),
+            multi_queue: tun_flags & libc::IFF_MULTI_QUEUE != 0,
+            packet_information: tun_flags & libc::IFF_NO_PI == 0,
+            vnet_hdr: tun_flags & libc::IFF_VNET_HDR != 0,
+            owner,
+            group,
+            persistent: tun_flags & libc::IFF_PERSIST != 0,
+        })
+    }
+}
+
+/// The TUN and TAP devices of the current namespace.
+///
+/// Their details are read from `/sys/class/net`, which shows the namespace sysfs was mounted
+/// in: after a bare `setns`, without mounting sysfs again, the devices are missing or mixed up
+/// with those of the same name of that other namespace.
+pub fn list_devices() -> Result<Vec<DeviceInfo>> {
+    let mut devices = Vec::new();
+    for link in Netlink::new()?.links()? {
+        if link.kind.as_deref() != Some("tun") {
+            continue;
+        }
+        match DeviceInfo::read(link) {
+            Ok(device) => devices.push(device),
+            // deleted since the dump
+            Err(Error::Io(err)) if err.raw_os_error() == Some(libc::ENOENT) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Inspect the TUN or TAP device `name` of the current namespace,
+/// with the same sysfs limit as [`list_devices`]
+pub fn device_info(name: &str) -> Result<DeviceInfo> {
+    let link = Netlink::new()?.link_by_name(name)?;
+    if link.kind.as_deref() != Some("tun") {
+        return Err(Error::InvalidName);
+    }
+
+    DeviceInfo::read(link)
+}
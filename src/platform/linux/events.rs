@@ -0,0 +1,159 @@
+use std::{
+    collections::VecDeque,
+    io,
+    os::fd::{AsRawFd, RawFd},
+    sync::{Arc, Mutex},
+};
+
+use super::netlink::{Address, Link, Netlink, Response};
+use crate::error::Result;
+
+/// The netlink groups the changes of a device are notified to
+pub(crate) const GROUPS: u32 =
+    (libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+
+/// A change of a device, whoever made it, e.g. `ip link set tun0 down`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Up,
+    Down,
+    Mtu(u32),
+    AddressAdded(Address),
+    AddressRemoved(Address),
+    /// The name reported by the [`Tun`](super::tun::Tun) is updated once this is received
+    Renamed(String),
+    /// Deleted or moved to another namespace, nothing follows
+    Removed,
+}
+
+/// The changes of a device as a blocking iterator, see [`Tun::events`](super::tun::Tun::events).
+/// An error such as ENOBUFS means some of them were lost
+pub struct Events {
+    nl: Netlink,
+    index: i32,
+    name: Arc<Mutex<String>>,
+    // the last state seen, RTM_NEWLINK carries the whole link rather than what changed
+    link: Link,
+    addresses: Vec<Address>,
+    pending: VecDeque<Event>,
+    removed: bool,
+}
+
+impl Events {
+    /// `nl` is subscribed to [`GROUPS`] before `link` and `addresses` are read,
+    /// a change in between is seen twice rather than missed
+    pub(crate) fn new(
+        nl: Netlink,
+        link: Link,
+        addresses: Vec<Address>,
+        name: Arc<Mutex<String>>,
+    ) -> Self {
+        Self {
+            nl,
+            index: link.index,
+            name,
+            link,
+            addresses,
+            pending: VecDeque::new(),
+            removed: false,
+        }
+    }
+
+    /// The iterator then yields WouldBlock errors, e.g. to poll the descriptor elsewhere
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.nl.set_nonblocking(nonblocking)
+    }
+
+    /// Read the next batch of notifications, those of the device are queued
+    pub(crate) fn recv(&mut self) -> io::Result<()> {
+        for res in self.nl.recv()? {
+            self.handle(&res);
+        }
+        Ok(())
+    }
+
+    /// The next event already received
+    pub(crate) fn pop(&mut self) -> Option<Event> {
+        self.pending.pop_front()
+    }
+
+    /// Whether the device is gone, once the pending events are popped nothing follows
+    pub(crate) fn is_removed(&self) -> bool {
+        self.removed
+    }
+
+    fn handle(&mut self, res: &Response) {
+        match res.ty {
+            libc::RTM_NEWLINK | libc::RTM_DELLINK => {
+                let link = match Link::parse(res) {
+                    Some(link) if link.index == self.index && !self.removed => link,
+                    _ => return,
+                };
+                if res.ty == libc::RTM_DELLINK {
+                    self.removed = true;
+                    self.pending.push_back(Event::Removed);
+                } else {
+                    self.update(link);
+                }
+            }
+            libc::RTM_NEWADDR => {
+                if let Some(addr) = Address::parse(res, self.index) {
+                    // also sent when the flags or lifetimes of an address change
+                    if !self.addresses.iter().any(|a| a.network == addr.network) {
+                        self.addresses.push(addr.clone());
+                        self.pending.push_back(Event::AddressAdded(addr));
+                    }
+                }
+            }
+            libc::RTM_DELADDR => {
+                if let Some(addr) = Address::parse(res, self.index) {
+                    self.addresses.retain(|a| a.network != addr.network);
+                    self.pending.push_back(Event::AddressRemoved(addr));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, link: Link) {
+        if link.name != self.link.name {
+            *self.name.lock().unwrap() = link.name.clone();
+            self.pending.push_back(Event::Renamed(link.name.clone()));
+        }
+        if link.mtu != self.link.mtu {
+            self.pending.push_back(Event::Mtu(link.mtu));
+        }
+
+        let up = link.flags & libc::IFF_UP as u32 != 0;
+        if up != (self.link.flags & libc::IFF_UP as u32 != 0) {
+            let event = if up { Event::Up } else { Event::Down };
+            self.pending.push_back(event);
+        }
+
+        self.link = link;
+    }
+}
+
+impl Iterator for Events {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pop() {
+                return Some(Ok(event));
+            }
+            if self.is_removed() {
+                return None;
+            }
+            if let Err(err) = self.recv() {
+                return Some(Err(err.into()));
+            }
+        }
+    }
+}
+
+impl AsRawFd for Events {
+    fn as_raw_fd(&self) -> RawFd {
+        self.nl.as_raw_fd()
+    }
+}
@@ -0,0 +1,163 @@
+use libc::sock_filter;
+use std::net::IpAddr;
+
+use crate::network::IpNetwork;
+
+// placeholder for jumps to the final drop instruction
+const DROP: u8 = u8::MAX;
+
+/// A classic BPF program run on the packets the kernel sends to the device,
+/// see [`Tun::attach_filter`](super::tun::Tun::attach_filter)
+#[derive(Debug, Clone)]
+pub struct Filter(pub(crate) Vec<sock_filter>);
+
+impl Filter {
+    /// A raw program, e.g. from `tcpdump -dd`. Offsets are from the start of the
+    /// packet, the Ethernet header for a TAP device, the IP header for a TUN one
+    pub fn new(program: Vec<sock_filter>) -> Self {
+        Self(program)
+    }
+
+    pub fn builder() -> FilterBuilder {
+        FilterBuilder::default()
+    }
+
+    pub fn instructions(&self) -> &[sock_filter] {
+        &self.0
+    }
+}
+
+/// Build a filter passing the IP packets matching every condition set, the
+/// offsets are relative to the IP header so it works for both layers
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FilterBuilder {
+    version: Option<u8>,
+    protocol: Option<u8>,
+    destination: Option<IpNetwork>,
+}
+
+impl FilterBuilder {
+    pub fn ipv4(mut self) -> Self {
+        self.version = Some(4);
+        self
+    }
+
+    pub fn ipv6(mut self) -> Self {
+        self.version = Some(6);
+        self
+    }
+
+    /// The IP protocol number, e.g. `libc::IPPROTO_UDP`. For IPv6 this is the
+    /// next header of the fixed header, extension headers are not followed
+    pub fn protocol(mut self, protocol: u8) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// Pass only packets to this subnet, which implies its IP version
+    pub fn destination(mut self, network: IpNetwork) -> Self {
+        self.version = Some(if network.is_ipv4() { 4 } else { 6 });
+        self.destination = Some(network);
+        self
+    }
+
+    pub fn build(self) -> Filter {
+        let mut program = Vec::new();
+
+        match self.version {
+            Some(version) => {
+                program.extend(check_version(version, DROP));
+                program.extend(self.checks(version));
+            }
+            None if self.protocol.is_none() => program.push(ret(u32::MAX)),
+            None => {
+                let v4 = self.checks(4);
+                program.extend(check_version(4, v4.len() as u8));
+                program.extend(v4);
+                program.extend(check_version(6, DROP));
+                program.extend(self.checks(6));
+            }
+        }
+
+        let drop = program.len();
+        for (i, insn) in program.iter_mut().enumerate() {
+            if insn.jf == DROP {
+                insn.jf = (drop - i - 1) as u8;
+            }
+        }
+        program.push(ret(0));
+
+        Filter(program)
+    }
+
+    /// The checks once the version is known, ending with accepting the packet
+    fn checks(&self, version: u8) -> Vec<sock_filter> {
+        let mut checks = Vec::new();
+
+        if let Some(protocol) = self.protocol {
+            checks.push(load(libc::BPF_B, if version == 4 { 9 } else { 6 }));
+            checks.push(jeq(protocol as u32, DROP));
+        }
+
+        match self
+            .destination
+            .map(|network| (network.network(), network.netmask()))
+        {
+            Some((IpAddr::V4(addr), IpAddr::V4(mask))) if !mask.is_unspecified() => {
+                checks.push(load(libc::BPF_W, 16));
+                checks.push(and(u32::from(mask)));
+                checks.push(jeq(u32::from(addr), DROP));
+            }
+            Some((IpAddr::V6(addr), IpAddr::V6(mask))) => {
+                let (addr, mask) = (addr.octets(), mask.octets());
+                for (i, (addr, mask)) in addr.chunks(4).zip(mask.chunks(4)).enumerate() {
+                    let mask = u32::from_be_bytes(mask.try_into().unwrap());
+                    if mask == 0 {
+                        break;
+                    }
+
+                    checks.push(load(libc::BPF_W, 24 + 4 * i as i32));
+                    checks.push(and(mask));
+                    checks.push(jeq(u32::from_be_bytes(addr.try_into().unwrap()), DROP));
+                }
+            }
+            _ => {}
+        }
+
+        checks.push(ret(u32::MAX));
+        checks
+    }
+}
+
+fn check_version(version: u8, jf: u8) -> [sock_filter; 3] {
+    [
+        load(libc::BPF_B, 0),
+        and(0xf0),
+        jeq((version as u32) << 4, jf),
+    ]
+}
+
+/// Load from the IP header, whatever the link layer is
+fn load(size: u32, offset: i32) -> sock_filter {
+    let k = (libc::BPF_NET_OFF + offset) as u32;
+    unsafe { libc::BPF_STMT((libc::BPF_LD | size | libc::BPF_ABS) as u16, k) }
+}
+
+fn and(k: u32) -> sock_filter {
+    unsafe { libc::BPF_STMT((libc::BPF_ALU | libc::BPF_AND | libc::BPF_K) as u16, k) }
+}
+
+fn jeq(k: u32, jf: u8) -> sock_filter {
+    unsafe {
+        libc::BPF_JUMP(
+            (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+            k,
+            0,
+            jf,
+        )
+    }
+}
+
+fn ret(k: u32) -> sock_filter {
+    unsafe { libc::BPF_STMT((libc::BPF_RET | libc::BPF_K) as u16, k) }
+}
@@ -0,0 +1,755 @@
+use super::sys::rtmsg;
+use crate::{
+    error::Result, ethernet::MacAddr, interface::Statistics, network::IpNetwork,
+    platform::posix::fd::Fd, route::Route, syscall,
+};
+use libc::{ifaddrmsg, ifinfomsg, nlmsgerr, nlmsghdr, rtattr, sockaddr_nl};
+use std::{
+    ffi::CStr,
+    io, mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    os::fd::{AsRawFd, RawFd},
+};
+
+const NLMSG_HDRLEN: usize = mem::size_of::<nlmsghdr>();
+const RTA_HDRLEN: usize = mem::size_of::<rtattr>();
+const RECV_BUF_SIZE: usize = 64 * 1024;
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn as_bytes<T: Copy>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+pub(crate) fn read<T: Copy>(data: &[u8]) -> Option<T> {
+    if data.len() < mem::size_of::<T>() {
+        return None;
+    }
+
+    Some(unsafe { std::ptr::read_unaligned(data.as_ptr() as *const T) })
+}
+
+fn ip_bytes(addr: &IpAddr) -> Vec<u8> {
+    match addr {
+        IpAddr::V4(addr) => addr.octets().to_vec(),
+        IpAddr::V6(addr) => addr.octets().to_vec(),
+    }
+}
+
+pub(crate) fn parse_ip(family: u8, data: &[u8]) -> Option<IpAddr> {
+    match family as i32 {
+        libc::AF_INET => read::<[u8; 4]>(data).map(|o| IpAddr::V4(Ipv4Addr::from(o))),
+        libc::AF_INET6 => read::<[u8; 16]>(data).map(|o| IpAddr::V6(Ipv6Addr::from(o))),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_str(data: &[u8]) -> String {
+    CStr::from_bytes_until_nul(data)
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|_| String::from_utf8_lossy(data).to_string())
+}
+
+/// A netlink request under construction
+pub(crate) struct Message {
+    buf: Vec<u8>,
+}
+
+impl Message {
+    pub fn new(ty: u16, flags: u16) -> Self {
+        let mut hdr: nlmsghdr = unsafe { mem::zeroed() };
+        hdr.nlmsg_type = ty;
+        hdr.nlmsg_flags = libc::NLM_F_REQUEST as u16 | flags;
+
+        let mut msg = Self { buf: Vec::new() };
+        msg.push(&hdr);
+        msg
+    }
+
+    fn pad(&mut self) {
+        self.buf.resize(align(self.buf.len()), 0);
+    }
+
+    /// Append a fixed size family header, e.g. `ifinfomsg`
+    pub fn push<T: Copy>(&mut self, value: &T) -> &mut Self {
+        self.buf.extend_from_slice(as_bytes(value));
+        self.pad();
+        self
+    }
+
+    pub fn attr(&mut self, ty: u16, data: &[u8]) -> &mut Self {
+        let rta = rtattr {
+            rta_len: (RTA_HDRLEN + data.len()) as u16,
+            rta_type: ty,
+        };
+        self.buf.extend_from_slice(as_bytes(&rta));
+        self.buf.extend_from_slice(data);
+        self.pad();
+        self
+    }
+
+    pub fn attr_u32(&mut self, ty: u16, value: u32) -> &mut Self {
+        self.attr(ty, &value.to_ne_bytes())
+    }
+
+    pub fn attr_str(&mut self, ty: u16, value: &str) -> &mut Self {
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+        self.attr(ty, &data)
+    }
+
+    pub fn attr_ip(&mut self, ty: u16, addr: &IpAddr) -> &mut Self {
+        self.attr(ty, &ip_bytes(addr))
+    }
+
+    /// Start an attribute holding the attributes appended until [`Message::end_nested`]
+    pub fn begin_nested(&mut self, ty: u16) -> usize {
+        let start = self.buf.len();
+        self.attr(ty, &[]);
+        start
+    }
+
+    pub fn end_nested(&mut self, start: usize) -> &mut Self {
+        let len = (self.buf.len() - start) as u16;
+        self.buf[start..start + 2].copy_from_slice(&len.to_ne_bytes());
+        self
+    }
+
+    fn finish(&mut self, seq: u32) -> &[u8] {
+        let len = self.buf.len() as u32;
+        self.buf[0..4].copy_from_slice(&len.to_ne_bytes());
+        // nlmsg_type(2) + nlmsg_flags(2)
+        self.buf[8..12].copy_from_slice(&seq.to_ne_bytes());
+        &self.buf
+    }
+
+    fn is_dump(&self) -> bool {
+        let flags = u16::from_ne_bytes([self.buf[6], self.buf[7]]);
+        flags & libc::NLM_F_DUMP as u16 == libc::NLM_F_DUMP as u16
+    }
+}
+
+/// A message received from the kernel, without its `nlmsghdr`
+pub(crate) struct Response {
+    pub ty: u16,
+    pub data: Vec<u8>,
+}
+
+impl Response {
+    pub fn header<T: Copy>(&self) -> Option<T> {
+        read(&self.data)
+    }
+
+    /// Iterate the attributes following a family header of type `T`
+    pub fn attrs<T>(&self) -> Attrs<'_> {
+        let offset = align(mem::size_of::<T>()).min(self.data.len());
+        Attrs(&self.data[offset..])
+    }
+}
+
+/// Iterator over a buffer of `rtattr`, yields the type and payload of each attribute
+pub(crate) struct Attrs<'a>(pub &'a [u8]);
+
+impl<'a> Iterator for Attrs<'a> {
+    type Item = (u16, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rta: rtattr = read(self.0)?;
+        let len = rta.rta_len as usize;
+        if len < RTA_HDRLEN || len > self.0.len() {
+            return None;
+        }
+
+        let data = &self.0[RTA_HDRLEN..len];
+        self.0 = &self.0[align(len).min(self.0.len())..];
+
+        // strip NLA_F_NESTED and NLA_F_NET_BYTEORDER
+        Some((rta.rta_type & 0x3fff, data))
+    }
+}
+
+pub(crate) struct Netlink {
+    fd: Fd,
+    seq: u32,
+}
+
+impl AsRawFd for Netlink {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl Netlink {
+    pub fn new() -> Result<Self> {
+        Self::subscribe(0)
+    }
+
+    /// A socket which also receives the notifications of the `RTMGRP_*` groups,
+    /// see [`Netlink::recv`]
+    pub fn subscribe(groups: u32) -> Result<Self> {
+        let fd = syscall!(socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE
+        ))?;
+        let fd = Fd::new(fd)?;
+
+        let mut addr: sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as _;
+        addr.nl_groups = groups;
+        syscall!(bind(
+            fd.as_raw_fd(),
+            &addr as *const sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<sockaddr_nl>() as libc::socklen_t
+        ))?;
+
+        Ok(Self { fd, seq: 0 })
+    }
+
+    /// Send a request and collect the replies until the kernel acks it or the dump is done
+    pub fn request(&mut self, msg: &mut Message) -> Result<Vec<Response>> {
+        self.seq = self.seq.wrapping_add(1);
+        let seq = self.seq;
+
+        if !msg.is_dump() {
+            let flags = u16::from_ne_bytes([msg.buf[6], msg.buf[7]]) | libc::NLM_F_ACK as u16;
+            msg.buf[6..8].copy_from_slice(&flags.to_ne_bytes());
+        }
+
+        let mut kernel: sockaddr_nl = unsafe { mem::zeroed() };
+        kernel.nl_family = libc::AF_NETLINK as _;
+        let buf = msg.finish(seq);
+        syscall!(sendto(
+            self.fd.as_raw_fd(),
+            buf.as_ptr() as *const _,
+            buf.len(),
+            0,
+            &kernel as *const sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<sockaddr_nl>() as libc::socklen_t
+        ))?;
+
+        let mut responses = Vec::new();
+        let mut buf = vec![0u8; RECV_BUF_SIZE];
+        loop {
+            let n = syscall!(recv(
+                self.fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut _,
+                buf.len(),
+                0
+            ))? as usize;
+
+            for (hdr, data) in Messages(&buf[..n]) {
+                if hdr.nlmsg_seq != seq {
+                    continue;
+                }
+
+                match hdr.nlmsg_type as i32 {
+                    libc::NLMSG_DONE => return Ok(responses),
+                    libc::NLMSG_ERROR => {
+                        let err: nlmsgerr = read(data)
+                            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+                        if err.error != 0 {
+                            return Err(io::Error::from_raw_os_error(-err.error).into());
+                        }
+                        return Ok(responses);
+                    }
+                    _ => responses.push(Response {
+                        ty: hdr.nlmsg_type,
+                        data: data.to_vec(),
+                    }),
+                }
+            }
+        }
+    }
+
+    /// The messages of the next datagram, e.g. notifications. ENOBUFS means some were lost
+    pub fn recv(&mut self) -> io::Result<Vec<Response>> {
+        let mut buf = vec![0u8; RECV_BUF_SIZE];
+        let n = syscall!(recv(
+            self.fd.as_raw_fd(),
+            buf.as_mut_ptr() as *mut _,
+            buf.len(),
+            0
+        ))? as usize;
+
+        Ok(Messages(&buf[..n])
+            .map(|(hdr, data)| Response {
+                ty: hdr.nlmsg_type,
+                data: data.to_vec(),
+            })
+            .collect())
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.fd.set_nonblocking(nonblocking)
+    }
+
+    pub fn link(&mut self, index: i32) -> Result<Link> {
+        let mut ifi: ifinfomsg = unsafe { mem::zeroed() };
+        ifi.ifi_family = libc::AF_UNSPEC as _;
+        ifi.ifi_index = index;
+
+        let mut msg = Message::new(libc::RTM_GETLINK, 0);
+        msg.push(&ifi);
+
+        self.request(&mut msg)?
+            .iter()
+            .find_map(Link::parse)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENODEV).into())
+    }
+
+    pub fn link_by_name(&mut self, name: &str) -> Result<Link> {
+        let mut ifi: ifinfomsg = unsafe { mem::zeroed() };
+        ifi.ifi_family = libc::AF_UNSPEC as _;
+
+        let mut msg = Message::new(libc::RTM_GETLINK, 0);
+        msg.push(&ifi).attr_str(libc::IFLA_IFNAME, name);
+
+        self.request(&mut msg)?
+            .iter()
+            .find_map(Link::parse)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENODEV).into())
+    }
+
+    /// Every link of the namespace
+    pub fn links(&mut self) -> Result<Vec<Link>> {
+        let ifi: ifinfomsg = unsafe { mem::zeroed() };
+
+        let mut msg = Message::new(libc::RTM_GETLINK, libc::NLM_F_DUMP as u16);
+        msg.push(&ifi);
+
+        Ok(self
+            .request(&mut msg)?
+            .iter()
+            .filter_map(Link::parse)
+            .collect())
+    }
+
+    /// Create a virtual link of type `kind`, e.g. `bridge`
+    pub fn new_link(&mut self, name: &str, kind: &str) -> Result<()> {
+        let mut ifi: ifinfomsg = unsafe { mem::zeroed() };
+        ifi.ifi_family = libc::AF_UNSPEC as _;
+
+        let mut msg = Message::new(
+            libc::RTM_NEWLINK,
+            (libc::NLM_F_CREATE | libc::NLM_F_EXCL) as u16,
+        );
+        msg.push(&ifi).attr_str(libc::IFLA_IFNAME, name);
+        let info = msg.begin_nested(libc::IFLA_LINKINFO);
+        msg.attr_str(libc::IFLA_INFO_KIND, kind);
+        msg.end_nested(info);
+
+        self.request(&mut msg)?;
+        Ok(())
+    }
+
+    pub fn del_link(&mut self, index: i32) -> Result<()> {
+        let mut ifi: ifinfomsg = unsafe { mem::zeroed() };
+        ifi.ifi_family = libc::AF_UNSPEC as _;
+        ifi.ifi_index = index;
+
+        let mut msg = Message::new(libc::RTM_DELLINK, 0);
+        msg.push(&ifi);
+
+        self.request(&mut msg)?;
+        Ok(())
+    }
+
+    /// Issue an RTM_NEWLINK for `index`, letting `f` append the attributes to change
+    pub fn set_link<F: FnOnce(&mut Message)>(
+        &mut self,
+        index: i32,
+        flags: u32,
+        change: u32,
+        f: F,
+    ) -> Result<()> {
+        let mut ifi: ifinfomsg = unsafe { mem::zeroed() };
+        ifi.ifi_family = libc::AF_UNSPEC as _;
+        ifi.ifi_index = index;
+        ifi.ifi_flags = flags;
+        ifi.ifi_change = change;
+
+        let mut msg = Message::new(libc::RTM_NEWLINK, 0);
+        msg.push(&ifi);
+        f(&mut msg);
+
+        self.request(&mut msg)?;
+        Ok(())
+    }
+
+    pub fn set_flags(&mut self, index: i32, flags: u32, change: u32) -> Result<()> {
+        self.set_link(index, flags, change, |_| {})
+    }
+
+    pub fn set_mtu(&mut self, index: i32, mtu: u32) -> Result<()> {
+        self.set_link(index, 0, 0, |msg| {
+            msg.attr_u32(libc::IFLA_MTU, mtu);
+        })
+    }
+
+    pub fn set_address(&mut self, index: i32, addr: &MacAddr) -> Result<()> {
+        self.set_link(index, 0, 0, |msg| {
+            msg.attr(libc::IFLA_ADDRESS, &addr.0);
+        })
+    }
+
+    /// Enslave `index` to the link `master`, released if 0
+    pub fn set_master(&mut self, index: i32, master: i32) -> Result<()> {
+        self.set_link(index, 0, 0, |msg| {
+            msg.attr_u32(libc::IFLA_MASTER, master as u32);
+        })
+    }
+
+    pub fn set_name(&mut self, index: i32, name: &str) -> Result<()> {
+        self.set_link(index, 0, 0, |msg| {
+            msg.attr_str(libc::IFLA_IFNAME, name);
+        })
+    }
+
+    /// All addresses of the interface `index`, the primary address of each family comes first
+    pub fn addresses(&mut self, index: i32) -> Result<Vec<Address>> {
+        let ifa: ifaddrmsg = unsafe { mem::zeroed() };
+
+        let mut msg = Message::new(libc::RTM_GETADDR, libc::NLM_F_DUMP as u16);
+        msg.push(&ifa);
+
+        Ok(self
+            .request(&mut msg)?
+            .iter()
+            .filter_map(|res| Address::parse(res, index))
+            .collect())
+    }
+
+    pub fn add_address(&mut self, index: i32, addr: &Address) -> Result<()> {
+        let mut msg = addr.message(
+            libc::RTM_NEWADDR,
+            (libc::NLM_F_CREATE | libc::NLM_F_EXCL) as u16,
+            index,
+        );
+        self.request(&mut msg)?;
+        Ok(())
+    }
+
+    pub fn del_address(&mut self, index: i32, addr: &Address) -> Result<()> {
+        let mut msg = addr.message(libc::RTM_DELADDR, 0, index);
+        self.request(&mut msg)?;
+        Ok(())
+    }
+
+    /// All unicast routes whose output interface is `index`, from every table
+    pub fn routes(&mut self, index: i32) -> Result<Vec<Route>> {
+        let rtm: rtmsg = unsafe { mem::zeroed() };
+
+        let mut msg = Message::new(libc::RTM_GETROUTE, libc::NLM_F_DUMP as u16);
+        msg.push(&rtm);
+
+        Ok(self
+            .request(&mut msg)?
+            .iter()
+            .filter_map(|res| parse_route(res, index))
+            .collect())
+    }
+
+    pub fn add_route(&mut self, index: i32, route: &Route) -> Result<()> {
+        let mut msg = route_message(
+            libc::RTM_NEWROUTE,
+            (libc::NLM_F_CREATE | libc::NLM_F_EXCL) as u16,
+            index,
+            route,
+        );
+        self.request(&mut msg)?;
+        Ok(())
+    }
+
+    pub fn del_route(&mut self, index: i32, route: &Route) -> Result<()> {
+        let mut msg = route_message(libc::RTM_DELROUTE, 0, index, route);
+        self.request(&mut msg)?;
+        Ok(())
+    }
+}
+
+fn route_message(ty: u16, flags: u16, index: i32, route: &Route) -> Message {
+    let table = route.table.unwrap_or(libc::RT_TABLE_MAIN as u32);
+
+    let mut rtm: rtmsg = unsafe { mem::zeroed() };
+    rtm.rtm_family = if route.is_ipv4() {
+        libc::AF_INET as u8
+    } else {
+        libc::AF_INET6 as u8
+    };
+    rtm.rtm_dst_len = route.prefix;
+    // tables above 255 only fit in RTA_TABLE
+    rtm.rtm_table = if table < 256 { table as u8 } else { 0 };
+    rtm.rtm_protocol = libc::RTPROT_BOOT;
+    rtm.rtm_type = libc::RTN_UNICAST;
+    rtm.rtm_scope = if ty == libc::RTM_DELROUTE {
+        // RT_SCOPE_NOWHERE, match any scope
+        255
+    } else if route.gateway.is_none() && route.is_ipv4() {
+        libc::RT_SCOPE_LINK
+    } else {
+        libc::RT_SCOPE_UNIVERSE
+    };
+
+    let mut msg = Message::new(ty, flags);
+    msg.push(&rtm)
+        .attr_u32(libc::RTA_TABLE, table)
+        .attr_u32(libc::RTA_OIF, index as u32);
+    if route.prefix > 0 {
+        msg.attr_ip(libc::RTA_DST, &route.destination);
+    }
+    if let Some(gateway) = route.gateway.as_ref() {
+        msg.attr_ip(libc::RTA_GATEWAY, gateway);
+    }
+    if let Some(source) = route.source.as_ref() {
+        msg.attr_ip(libc::RTA_PREFSRC, source);
+    }
+    if let Some(metric) = route.metric {
+        msg.attr_u32(libc::RTA_PRIORITY, metric);
+    }
+
+    msg
+}
+
+fn parse_route(res: &Response, index: i32) -> Option<Route> {
+    if res.ty != libc::RTM_NEWROUTE {
+        return None;
+    }
+
+    let rtm: rtmsg = res.header()?;
+    if rtm.rtm_type != libc::RTN_UNICAST {
+        return None;
+    }
+
+    let mut route = Route::new(parse_ip(rtm.rtm_family, &[0; 16])?, rtm.rtm_dst_len);
+    let mut table = rtm.rtm_table as u32;
+    let mut oif = None;
+
+    for (ty, data) in res.attrs::<rtmsg>() {
+        match ty {
+            libc::RTA_DST => route.destination = parse_ip(rtm.rtm_family, data)?,
+            libc::RTA_OIF => oif = read::<u32>(data),
+            libc::RTA_GATEWAY => route.gateway = parse_ip(rtm.rtm_family, data),
+            libc::RTA_PREFSRC => route.source = parse_ip(rtm.rtm_family, data),
+            libc::RTA_PRIORITY => route.metric = read(data),
+            libc::RTA_TABLE => table = read(data).unwrap_or(table),
+            _ => {}
+        }
+    }
+
+    if oif != Some(index as u32) {
+        return None;
+    }
+    route.table = (table != libc::RT_TABLE_MAIN as u32).then_some(table);
+
+    Some(route)
+}
+
+/// Iterator over the netlink messages packed in a datagram
+struct Messages<'a>(&'a [u8]);
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = (nlmsghdr, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let hdr: nlmsghdr = read(self.0)?;
+        let len = hdr.nlmsg_len as usize;
+        if len < NLMSG_HDRLEN || len > self.0.len() {
+            return None;
+        }
+
+        let data = &self.0[NLMSG_HDRLEN..len];
+        self.0 = &self.0[align(len).min(self.0.len())..];
+
+        Some((hdr, data))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Link {
+    pub index: i32,
+    pub name: String,
+    pub flags: u32,
+    pub mtu: u32,
+    /// None for a device without hardware address, e.g. a TUN one
+    pub address: Option<MacAddr>,
+    /// The bridge or bond it is enslaved to
+    pub master: Option<i32>,
+    /// The driver of a virtual link, e.g. `tun` or `bridge`
+    pub kind: Option<String>,
+    pub stats: Option<Statistics>,
+}
+
+impl Link {
+    /// From RTM_NEWLINK, or RTM_DELLINK for a notification
+    pub fn parse(res: &Response) -> Option<Self> {
+        if res.ty != libc::RTM_NEWLINK && res.ty != libc::RTM_DELLINK {
+            return None;
+        }
+
+        let ifi: ifinfomsg = res.header()?;
+        let mut link = Link {
+            index: ifi.ifi_index,
+            flags: ifi.ifi_flags,
+            ..Default::default()
+        };
+
+        for (ty, data) in res.attrs::<ifinfomsg>() {
+            match ty {
+                libc::IFLA_IFNAME => link.name = parse_str(data),
+                libc::IFLA_MTU => link.mtu = read(data).unwrap_or_default(),
+                libc::IFLA_ADDRESS => link.address = read::<[u8; 6]>(data).map(MacAddr),
+                libc::IFLA_MASTER => link.master = read(data).filter(|&master: &i32| master != 0),
+                libc::IFLA_STATS64 => link.stats = read::<[u64; 9]>(data).map(parse_stats),
+                libc::IFLA_LINKINFO => {
+                    link.kind = Attrs(data)
+                        .find(|(ty, _)| *ty == libc::IFLA_INFO_KIND)
+                        .map(|(_, kind)| parse_str(kind))
+                }
+                _ => {}
+            }
+        }
+
+        Some(link)
+    }
+}
+
+/// The leading fields of `rtnl_link_stats64`
+fn parse_stats(stats: [u64; 9]) -> Statistics {
+    Statistics {
+        rx_packets: stats[0],
+        tx_packets: stats[1],
+        rx_bytes: stats[2],
+        tx_bytes: stats[3],
+        rx_errors: stats[4],
+        tx_errors: stats[5],
+        rx_dropped: stats[6],
+        tx_dropped: stats[7],
+        multicast: stats[8],
+    }
+}
+
+/// An address assigned to the interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub network: IpNetwork,
+    /// The remote end of a point-to-point link
+    pub peer: Option<IpAddr>,
+    pub broadcast: Option<Ipv4Addr>,
+    /// IPv4 only, e.g. `tun0:1`, defaults to the interface name
+    pub label: Option<String>,
+    /// Set by the kernel on additional IPv4 addresses within the subnet of the primary one
+    pub secondary: bool,
+}
+
+impl Address {
+    pub fn new(network: IpNetwork) -> Self {
+        Self {
+            network,
+            peer: None,
+            broadcast: None,
+            label: None,
+            secondary: false,
+        }
+    }
+
+    pub fn peer<A: Into<IpAddr>>(mut self, peer: A) -> Self {
+        self.peer = Some(peer.into());
+        self
+    }
+
+    pub fn broadcast(mut self, broadcast: Ipv4Addr) -> Self {
+        self.broadcast = Some(broadcast);
+        self
+    }
+
+    pub fn label<S: AsRef<str>>(mut self, label: S) -> Self {
+        self.label = Some(label.as_ref().into());
+        self
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.network.addr()
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.network.prefix()
+    }
+
+    /// From RTM_NEWADDR, or RTM_DELADDR for a notification
+    pub(crate) fn parse(res: &Response, index: i32) -> Option<Self> {
+        if res.ty != libc::RTM_NEWADDR && res.ty != libc::RTM_DELADDR {
+            return None;
+        }
+
+        let ifa: ifaddrmsg = res.header()?;
+        if ifa.ifa_index != index as u32 {
+            return None;
+        }
+
+        let mut local = None;
+        let mut address = None;
+        let mut broadcast = None;
+        let mut label = None;
+
+        for (ty, data) in res.attrs::<ifaddrmsg>() {
+            match ty {
+                libc::IFA_LOCAL => local = parse_ip(ifa.ifa_family, data),
+                libc::IFA_ADDRESS => address = parse_ip(ifa.ifa_family, data),
+                libc::IFA_BROADCAST => {
+                    if let Some(IpAddr::V4(addr)) = parse_ip(ifa.ifa_family, data) {
+                        broadcast = Some(addr);
+                    }
+                }
+                libc::IFA_LABEL => label = Some(parse_str(data)),
+                _ => {}
+            }
+        }
+
+        // IFA_ADDRESS is the peer address if IFA_LOCAL is present and differs
+        let local_addr = local.or(address)?;
+        let peer = address.filter(|addr| *addr != local_addr);
+        let is_ipv4 = ifa.ifa_family as i32 == libc::AF_INET;
+
+        Some(Self {
+            network: IpNetwork::new(local_addr, ifa.ifa_prefixlen).ok()?,
+            peer,
+            broadcast,
+            label,
+            secondary: is_ipv4 && ifa.ifa_flags as u32 & libc::IFA_F_SECONDARY != 0,
+        })
+    }
+
+    fn message(&self, ty: u16, flags: u16, index: i32) -> Message {
+        let local = self.addr();
+
+        let mut ifa: ifaddrmsg = unsafe { mem::zeroed() };
+        ifa.ifa_family = match local {
+            IpAddr::V4(_) => libc::AF_INET as u8,
+            IpAddr::V6(_) => libc::AF_INET6 as u8,
+        };
+        ifa.ifa_prefixlen = self.prefix();
+        ifa.ifa_index = index as u32;
+
+        let mut msg = Message::new(ty, flags);
+        msg.push(&ifa)
+            .attr_ip(libc::IFA_LOCAL, &local)
+            .attr_ip(libc::IFA_ADDRESS, self.peer.as_ref().unwrap_or(&local));
+        if let Some(broadcast) = self.broadcast {
+            msg.attr_ip(libc::IFA_BROADCAST, &IpAddr::V4(broadcast));
+        }
+        if let Some(label) = self.label.as_ref().filter(|_| local.is_ipv4()) {
+            msg.attr_str(libc::IFA_LABEL, label);
+        }
+
+        msg
+    }
+}
+
+impl From<IpNetwork> for Address {
+    fn from(network: IpNetwork) -> Self {
+        Self::new(network)
+    }
+}
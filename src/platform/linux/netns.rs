@@ -0,0 +1,90 @@
+use std::{
+    ffi::CString,
+    os::{fd::RawFd, unix::ffi::OsStrExt},
+    path::PathBuf,
+};
+
+use crate::{error::Result, platform::posix::fd::Fd, syscall};
+
+/// A network namespace, by path such as `/var/run/netns/foo` or by descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum NetNs {
+    Path(PathBuf),
+    /// Borrowed, the descriptor is duplicated when used. Only meaningful in the
+    /// current process, it can not be serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Fd(RawFd),
+}
+
+impl NetNs {
+    pub(crate) fn open(&self) -> Result<Fd> {
+        let fd = match self {
+            NetNs::Path(path) => {
+                let path = CString::new(path.as_os_str().as_bytes())?;
+                syscall!(open(path.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC))?
+            }
+            NetNs::Fd(fd) => syscall!(fcntl(*fd, libc::F_DUPFD_CLOEXEC, 0))?,
+        };
+
+        Fd::new(fd)
+    }
+}
+
+impl From<PathBuf> for NetNs {
+    fn from(path: PathBuf) -> Self {
+        NetNs::Path(path)
+    }
+}
+
+impl From<&str> for NetNs {
+    fn from(path: &str) -> Self {
+        NetNs::Path(path.into())
+    }
+}
+
+/// Run `f` with the calling thread in the namespace `ns`, if any. Sockets opened
+/// by `f` stay in that namespace once the thread is back to its own
+pub(crate) fn enter<T, F: FnOnce() -> Result<T>>(ns: Option<&Fd>, f: F) -> Result<T> {
+    let ns = match ns {
+        Some(ns) => ns,
+        None => return f(),
+    };
+
+    let current = syscall!(open(
+        c"/proc/thread-self/ns/net".as_ptr(),
+        libc::O_RDONLY | libc::O_CLOEXEC
+    ))?;
+    let current = Restore(Some(Fd::new(current)?));
+
+    syscall!(setns(ns.0, libc::CLONE_NEWNET))?;
+    let res = f();
+    // reported rather than silently misconfiguring everything after in the wrong namespace
+    current.restore()?;
+
+    res
+}
+
+/// Puts the calling thread back in its namespace when dropped, e.g. if `f` panics
+struct Restore(Option<Fd>);
+
+impl Restore {
+    fn restore(mut self) -> Result<()> {
+        if let Some(ns) = self.0.take() {
+            syscall!(setns(ns.0, libc::CLONE_NEWNET))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(ns) = self.0.take() {
+            let _ = syscall!(setns(ns.0, libc::CLONE_NEWNET));
+        }
+    }
+}
@@ -1,27 +1,61 @@
-use libc::{c_int, ifreq};
-
-nix::ioctl_read_bad!(siocgifflags, 0x8913, ifreq);      // get flags
+use libc::{c_int, c_uchar, c_uint, ifreq, in6_ifreq, sock_fprog};
+
+// rtmsg can not be found from libc
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct rtmsg {
+    pub rtm_family: c_uchar,
+    pub rtm_dst_len: c_uchar,
+    pub rtm_src_len: c_uchar,
+    pub rtm_tos: c_uchar,
+    pub rtm_table: c_uchar,
+    pub rtm_protocol: c_uchar,
+    pub rtm_scope: c_uchar,
+    pub rtm_type: c_uchar,
+    pub rtm_flags: c_uint,
+}
+
+nix::ioctl_read_bad!(siocgifflags, 0x8913, ifreq); // get flags
 nix::ioctl_write_ptr_bad!(siocsifflags, 0x8914, ifreq); // set flags
 
-nix::ioctl_read_bad!(siocgifaddr, 0x8915, ifreq);       // get PA address
-nix::ioctl_write_ptr_bad!(siocsifaddr, 0x8916, ifreq);  // set PA address
+nix::ioctl_read_bad!(siocgifaddr, 0x8915, ifreq); // get PA address
+nix::ioctl_write_ptr_bad!(siocsifaddr, 0x8916, ifreq); // set PA address
+
+nix::ioctl_read_bad!(siocgifdstaddr, 0x8917, ifreq); // get remote PA address
+nix::ioctl_write_ptr_bad!(siocsifdstaddr, 0x8918, ifreq); // set remote PA address
+
+nix::ioctl_read_bad!(siocgifbrdaddr, 0x8919, ifreq); // get broadcast PA address
+nix::ioctl_write_ptr_bad!(siocsifbrdaddr, 0x891a, ifreq); // set broadcast PA address
+
+nix::ioctl_read_bad!(siocgifnetmask, 0x891b, ifreq); // get network PA mask
+nix::ioctl_write_ptr_bad!(siocsifnetmask, 0x891c, ifreq); // set network PA mask
 
-nix::ioctl_read_bad!(siocgifdstaddr, 0x8917, ifreq);        // get remote PA address
-nix::ioctl_write_ptr_bad!(siocsifdstaddr, 0x8918, ifreq);   // set remote PA address
+nix::ioctl_read_bad!(siocgifmtu, 0x8921, ifreq); // get MTU size
+nix::ioctl_write_ptr_bad!(siocsifmtu, 0x8922, ifreq); // set MTU size
 
-nix::ioctl_read_bad!(siocgifbrdaddr, 0x8919, ifreq);        // get broadcast PA address
-nix::ioctl_write_ptr_bad!(siocsifbrdaddr, 0x891a, ifreq);   // set broadcast PA address
+nix::ioctl_read_bad!(siocgifname, 0x8910, ifreq); // get iface name
+nix::ioctl_write_ptr_bad!(siocsifname, 0x8923, ifreq); // set interface name
 
-nix::ioctl_read_bad!(siocgifnetmask, 0x891b, ifreq);        // get network PA mask
-nix::ioctl_write_ptr_bad!(siocsifnetmask, 0x891c, ifreq);   // set network PA mask
+nix::ioctl_read_bad!(siocgifindex, 0x8933, ifreq); // name -> if_index mapping
 
-nix::ioctl_read_bad!(siocgifmtu, 0x8921, ifreq);        // get MTU size
-nix::ioctl_write_ptr_bad!(siocsifmtu, 0x8922, ifreq);   // set MTU size
+nix::ioctl_read_bad!(siocgifhwaddr, 0x8927, ifreq); // get hardware address
+nix::ioctl_write_ptr_bad!(siocsifhwaddr, 0x8924, ifreq); // set hardware address
 
-nix::ioctl_read_bad!(siocgifname, 0x8910, ifreq);       // get iface name
-nix::ioctl_write_ptr_bad!(siocsifname, 0x8923, ifreq);  // set interface name
+nix::ioctl_write_ptr_bad!(siocsifaddr_in6, 0x8916, in6_ifreq); // add IPv6 address
+nix::ioctl_write_ptr_bad!(siocdifaddr_in6, 0x8936, in6_ifreq); // delete IPv6 address
 
 nix::ioctl_write_ptr!(tunsetiff, b'T', 202, c_int);
-nix::ioctl_write_ptr!(tunsetpersist, b'T', 203, c_int);
-nix::ioctl_write_ptr!(tunsetowner, b'T', 204, c_int);
-nix::ioctl_write_ptr!(tunsetgroup, b'T', 205, c_int);
+nix::ioctl_read!(tungetiff, b'T', 210, c_uint);
+nix::ioctl_read!(tungetvnethdrsz, b'T', 215, c_int);
+nix::ioctl_write_ptr!(tunsetqueue, b'T', 217, c_int);
+// declared with _IOR although the program fd is read by the kernel
+nix::ioctl_read!(tunsetsteeringebpf, b'T', 224, c_int);
+nix::ioctl_read!(tunsetfilterebpf, b'T', 225, c_int);
+nix::ioctl_write_ptr!(tunattachfilter, b'T', 213, sock_fprog);
+nix::ioctl_write_ptr!(tundetachfilter, b'T', 214, sock_fprog);
+// the following take their argument by value rather than through a pointer
+nix::ioctl_write_int!(tunsetpersist, b'T', 203);
+nix::ioctl_write_int!(tunsetowner, b'T', 204);
+nix::ioctl_write_int!(tunsetgroup, b'T', 205);
+nix::ioctl_write_int!(tunsetoffload, b'T', 208);
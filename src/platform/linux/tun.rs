@@ -1,25 +1,74 @@
 use crate::{
-    address::{Ipv4AddrExt, SockAddrExt},
+    address::{Ipv4AddrExt, Ipv6AddrExt, SockAddrExt},
     configuration::{Configuration, Layer},
     error::{Error, Result},
-    interface::Interface,
+    ethernet::MacAddr,
+    interface::{Interface, InterfaceFlags, Statistics},
+    network::IpNetwork,
     platform::posix::fd::Fd,
+    route::Route,
     syscall,
+    vnet::{Offloads, VirtioNetHdr},
 };
-use libc::{c_int, c_short, IFNAMSIZ};
+use libc::{c_int, c_short, c_uint, IFNAMSIZ};
 use std::{
     ffi::CStr,
     io::{self, Read, Write},
-    net::Ipv4Addr,
+    mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     os::fd::{AsRawFd, RawFd},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicI32, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
 };
 
+pub use super::bridge::{create_bridge, delete_bridge};
+pub use super::devices::{device_info, list_devices, DeviceInfo};
+use super::events;
+pub use super::events::{Event, Events};
+pub use super::filter::{Filter, FilterBuilder};
+pub use super::netlink::Address;
+use super::netlink::Netlink;
+use super::netns;
+pub use super::netns::NetNs;
 use super::sys::*;
 
-#[derive(Debug, Clone, Copy, Default)]
+/// How the interface is configured once it is created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Backend {
+    /// rtnetlink, falls back to `Ioctl` if a netlink socket can not be opened
+    #[default]
+    Netlink,
+    /// legacy SIOCSIF* ioctls, limited to a single IPv4 address
+    Ioctl,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct TunConf {
     pub(crate) packet_information: bool,
+    pub(crate) backend: Backend,
+    // keep the device once the last queue is closed
+    pub(crate) persist: bool,
+    pub(crate) owner: Option<u32>,
+    pub(crate) group: Option<u32>,
+    // open an existing device instead of creating one
+    pub(crate) attach: bool,
+    pub(crate) vnet_hdr: bool,
+    pub(crate) offloads: Offloads,
+    // IFF_MULTI_QUEUE even with a single queue
+    pub(crate) multi_queue: bool,
+    pub(crate) netns: Option<NetNs>,
 }
 
 impl TunConf {
@@ -27,6 +76,66 @@ impl TunConf {
         self.packet_information = value;
         self
     }
+
+    pub fn backend(&mut self, backend: Backend) -> &mut Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Keep the device after it is closed, see [`Tun::destroy_persistent`]
+    pub fn persist(&mut self, value: bool) -> &mut Self {
+        self.persist = value;
+        self
+    }
+
+    /// The uid allowed to attach to the device without CAP_NET_ADMIN
+    pub fn owner(&mut self, uid: u32) -> &mut Self {
+        self.owner = Some(uid);
+        self
+    }
+
+    /// The gid allowed to attach to the device without CAP_NET_ADMIN
+    pub fn group(&mut self, gid: u32) -> &mut Self {
+        self.group = Some(gid);
+        self
+    }
+
+    /// Open the existing device with the configured name, e.g. a persistent one,
+    /// instead of creating it. The layer, packet information and multi-queue
+    /// flags are taken from the device rather than the configuration
+    pub fn attach(&mut self, value: bool) -> &mut Self {
+        self.attach = value;
+        self
+    }
+
+    /// Create a multi-queue device even if a single queue is configured,
+    /// more can be opened later with [`Tun::open_queue`]
+    pub fn multi_queue(&mut self, value: bool) -> &mut Self {
+        self.multi_queue = value;
+        self
+    }
+
+    /// Create and configure the device in another network namespace, it is still
+    /// used from the current one. [`TunConf::attach`] looks the device up in the
+    /// sysfs of the current namespace, which may not show it
+    pub fn netns<N: Into<NetNs>>(&mut self, ns: N) -> &mut Self {
+        self.netns = Some(ns.into());
+        self
+    }
+
+    /// Prefix every packet with a [`VirtioNetHdr`], see [`Tun::read_vnet`]
+    pub fn vnet_hdr(&mut self, value: bool) -> &mut Self {
+        self.vnet_hdr = value;
+        self
+    }
+
+    /// Offloads enabled with TUNSETOFFLOAD, implies the vnet header. With
+    /// segmentation offloads packets may be up to 64KB regardless of the MTU
+    pub fn offloads(&mut self, offloads: Offloads) -> &mut Self {
+        self.vnet_hdr = true;
+        self.offloads = offloads;
+        self
+    }
 }
 
 pub struct Queue {
@@ -54,10 +163,128 @@ impl AsRawFd for Queue {
     }
 }
 
-pub struct Tun {
+/// Routes installed from the configuration, removed once the last queue is dropped
+struct ConfiguredRoutes {
+    index: Arc<AtomicI32>,
+    netns: Arc<Mutex<Option<Arc<Fd>>>>,
+    routes: Mutex<Vec<Route>>,
+}
+
+impl ConfiguredRoutes {
+    fn push(&self, route: Route) {
+        self.routes.lock().unwrap().push(route);
+    }
+}
+
+impl Drop for ConfiguredRoutes {
+    fn drop(&mut self) {
+        let routes = self.routes.get_mut().unwrap();
+        if routes.is_empty() {
+            return;
+        }
+
+        let index = self.index.load(Ordering::Relaxed);
+        let netns = self.netns.lock().unwrap().clone();
+        if let Ok(mut nl) = netns::enter(netns.as_deref(), Netlink::new) {
+            for route in routes.iter() {
+                let _ = nl.del_route(index, route);
+            }
+        }
+    }
+}
+
+/// What went through the queues of a device, seen from the interface as the kernel
+/// counters are: a packet written to a queue is received by the interface
+#[derive(Default)]
+struct Counters {
+    rx_packets: AtomicU64,
+    rx_bytes: AtomicU64,
+    rx_errors: AtomicU64,
+    tx_packets: AtomicU64,
+    tx_bytes: AtomicU64,
+    tx_errors: AtomicU64,
+}
+
+impl Counters {
+    fn written(&self, res: Result<&usize, &io::Error>, prefix: usize) {
+        Self::count(
+            &self.rx_packets,
+            &self.rx_bytes,
+            &self.rx_errors,
+            res,
+            prefix,
+        );
+    }
+
+    fn read(&self, res: Result<&usize, &io::Error>, prefix: usize) {
+        Self::count(
+            &self.tx_packets,
+            &self.tx_bytes,
+            &self.tx_errors,
+            res,
+            prefix,
+        );
+    }
+
+    /// `prefix` bytes of packet information and virtio-net header are not part of the packet
+    fn count(
+        packets: &AtomicU64,
+        bytes: &AtomicU64,
+        errors: &AtomicU64,
+        res: Result<&usize, &io::Error>,
+        prefix: usize,
+    ) {
+        match res {
+            Ok(n) => {
+                packets.fetch_add(1, Ordering::Relaxed);
+                bytes.fetch_add(n.saturating_sub(prefix) as u64, Ordering::Relaxed);
+            }
+            // retried by the caller
+            Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock) => {}
+            Err(err) if matches!(err.kind(), io::ErrorKind::Interrupted) => {}
+            Err(_) => {
+                errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Statistics {
+        Statistics {
+            rx_packets: self.rx_packets.load(Ordering::Relaxed),
+            tx_packets: self.tx_packets.load(Ordering::Relaxed),
+            rx_bytes: self.rx_bytes.load(Ordering::Relaxed),
+            tx_bytes: self.tx_bytes.load(Ordering::Relaxed),
+            rx_errors: self.rx_errors.load(Ordering::Relaxed),
+            tx_errors: self.tx_errors.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+/// Configures the interface of a device apart from its queues, cloned to do so from
+/// anywhere, e.g. once the queues are moved to worker threads or wrapped in `AsyncTun`
+#[derive(Clone)]
+pub struct TunController {
+    configured_routes: Arc<ConfiguredRoutes>,
     name: Arc<Mutex<String>>,
-    queue: Queue,
+    // both change when the device moves to another namespace
+    index: Arc<AtomicI32>,
+    netns: Arc<Mutex<Option<Arc<Fd>>>>,
+    layer: Layer,
+    multi_queue: bool,
     ctl: Arc<Mutex<Fd>>,
+    netlink: Option<Arc<Mutex<Netlink>>>,
+}
+
+/// A queue of a device, the methods of its [`TunController`] are forwarded
+/// or reachable through [`Tun::controller`]
+pub struct Tun {
+    // dropped before the queue unless cloned, the routes are gone with the interface anyway
+    controller: TunController,
+    vnet_hdr_len: usize,
+    queue: Queue,
+    // shared by the queues of the device opened in this process
+    counters: Arc<Counters>,
 }
 
 impl Tun {
@@ -67,80 +294,330 @@ impl Tun {
     }
 
     pub fn new_multi_queue(config: &Configuration) -> Result<Vec<Self>> {
-        let ctl_fd = syscall!(socket(libc::AF_INET, libc::SOCK_DGRAM, 0))?;
-        let ctl = Fd::new(ctl_fd)?;
-        let ctl = Arc::new(Mutex::new(ctl));
-        let name = Arc::new(Mutex::new(String::new()));
-
-        let mut tuns = Vec::new();
-        let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
-
-        if let Some(name) = config.name.as_ref() {
-            unsafe {
-                std::ptr::copy_nonoverlapping(
-                    name.as_ptr() as *const _,
-                    ifr.ifr_name.as_mut_ptr(),
-                    name.len(),
-                )
-            }
+        let netns = match &config.platform.netns {
+            Some(ns) => Some(Arc::new(ns.open()?)),
+            None => None,
         };
 
-        let tun_type: c_short = config.layer.into();
-        let queue_nums = config.queues.unwrap_or(1);
-        if queue_nums < 1 {
-            return Err(Error::InvalidQueuesNumber);
-        }
+        // the device and the sockets configuring it are created in the namespace
+        netns::enter(netns.as_deref(), || Self::create(config, netns.clone()))
+    }
+
+    fn create(config: &Configuration, netns: Option<Arc<Fd>>) -> Result<Vec<Self>> {
+        let mut queues = Vec::new();
+        let mut ifr = ifreq_for(config.name.as_deref().unwrap_or_default());
+
+        if let Some(fd) = config.raw_fd {
+            // an already attached queue, e.g. inherited from a supervisor process
+            let tun = Fd::new(fd)?;
+            unsafe { tungetiff(fd, &mut ifr as *mut libc::ifreq as *mut c_uint) }?;
+
+            // TUNGETIFF reports IFF_NOFILTER which shares its bit with IFF_NO_PI,
+            // only trust it for the name
+            let name = unsafe { CStr::from_ptr(ifr.ifr_name.as_ptr()) }.to_string_lossy();
+            ifr.ifr_ifru.ifru_flags = tun_flags(&name)?;
 
-        let pi = config.platform.packet_information;
-        ifr.ifr_ifru.ifru_flags = tun_type
-            | if pi { 0 } else { libc::IFF_NO_PI as c_short }
-            | if queue_nums > 1 {
-                libc::IFF_MULTI_QUEUE as c_short
+            queues.push(Queue {
+                tun,
+                pi_enabled: unsafe { ifr.ifr_ifru.ifru_flags } & libc::IFF_NO_PI as c_short == 0,
+            });
+        } else {
+            let queue_nums = config.queues.unwrap_or(1);
+            if queue_nums < 1 {
+                return Err(Error::InvalidQueuesNumber);
+            }
+
+            ifr.ifr_ifru.ifru_flags = if config.platform.attach {
+                tun_flags(config.name.as_deref().ok_or(Error::InvalidName)?)?
             } else {
-                0
+                let tun_type: c_short = config.layer.into();
+                tun_type
+                    | if config.platform.packet_information {
+                        0
+                    } else {
+                        libc::IFF_NO_PI as c_short
+                    }
+                    | if queue_nums > 1 || config.platform.multi_queue {
+                        libc::IFF_MULTI_QUEUE as c_short
+                    } else {
+                        0
+                    }
+                    | if config.platform.vnet_hdr {
+                        libc::IFF_VNET_HDR as c_short
+                    } else {
+                        0
+                    }
             };
+            let pi = unsafe { ifr.ifr_ifru.ifru_flags } & libc::IFF_NO_PI as c_short == 0;
 
-        for _ in 0..queue_nums {
-            let tun_fd = syscall!(open(b"/dev/net/tun\0".as_ptr() as *const _, libc::O_RDWR))?;
+            for _ in 0..queue_nums {
+                let tun_fd = syscall!(open(c"/dev/net/tun".as_ptr(), libc::O_RDWR))?;
+                let tun = Fd::new(tun_fd)?;
 
-            unsafe { tunsetiff(tun_fd, &mut ifr as *mut libc::ifreq as *mut c_int) }?;
+                unsafe { tunsetiff(tun_fd, &mut ifr as *mut libc::ifreq as *mut c_int) }?;
 
-            let queue = Queue {
-                tun: Fd::new(tun_fd)?,
-                pi_enabled: pi,
-            };
-            let tun = Self {
-                name: name.clone(),
-                queue,
-                ctl: ctl.clone(),
-            };
-            tuns.push(tun);
+                queues.push(Queue {
+                    tun,
+                    pi_enabled: pi,
+                });
+            }
+        }
+
+        let tun_fd = queues[0].as_raw_fd();
+        if let Some(uid) = config.platform.owner {
+            unsafe { tunsetowner(tun_fd, uid as _) }?;
+        }
+        if let Some(gid) = config.platform.group {
+            unsafe { tunsetgroup(tun_fd, gid as _) }?;
+        }
+        if config.platform.persist {
+            unsafe { tunsetpersist(tun_fd, 1) }?;
+        }
+        if config.platform.offloads != Offloads::default() {
+            unsafe { tunsetoffload(tun_fd, offload_bits(&config.platform.offloads) as _) }?;
+        }
+
+        let flags = unsafe { ifr.ifr_ifru.ifru_flags };
+        let vnet_hdr_len = if flags & libc::IFF_VNET_HDR as c_short != 0 {
+            let mut len: c_int = 0;
+            unsafe { tungetvnethdrsz(tun_fd, &mut len) }?;
+            len as usize
+        } else {
+            0
+        };
+
+        let ctl = Fd::new(syscall!(socket(libc::AF_INET, libc::SOCK_DGRAM, 0))?)?;
+        unsafe { siocgifindex(ctl.as_raw_fd(), &mut ifr) }?;
+
+        let mut tuns = Self::from_queues(
+            queues,
+            ctl,
+            &ifr,
+            flags,
+            vnet_hdr_len,
+            netns,
+            config.platform.backend,
+        )?;
+
+        tuns[0].configure(config)?;
+        if let Some(bridge) = config.bridge.as_ref() {
+            tuns[0].enslave(bridge)?;
+        }
+        for (addr, prefix) in config.ipv6.iter() {
+            tuns[0].add_ipv6_address(*addr, *prefix)?;
+        }
+
+        for network in config.addresses.iter() {
+            tuns[0].add_address(&Address::from(*network))?;
+        }
+
+        for route in config.routes.iter() {
+            tuns[0].add_route(route)?;
+            tuns[0].controller.configured_routes.push(route.clone());
         }
 
+        Ok(tuns)
+    }
+
+    /// Share the queues of a device, `ifr` holds its name and index
+    fn from_queues(
+        queues: Vec<Queue>,
+        ctl: Fd,
+        ifr: &libc::ifreq,
+        flags: c_short,
+        vnet_hdr_len: usize,
+        netns: Option<Arc<Fd>>,
+        backend: Backend,
+    ) -> Result<Vec<Self>> {
+        let layer = if flags & libc::IFF_TAP as c_short != 0 {
+            Layer::L2
+        } else {
+            Layer::L3
+        };
+        let multi_queue = flags & libc::IFF_MULTI_QUEUE as c_short != 0;
+
         let name = unsafe {
             CStr::from_ptr(ifr.ifr_name.as_ptr())
                 .to_string_lossy()
                 .to_string()
         };
-        *tuns[0].name.lock().unwrap() = name;
+        let name = Arc::new(Mutex::new(name));
+        let index = Arc::new(AtomicI32::new(unsafe { ifr.ifr_ifru.ifru_ifindex }));
+        let netns = Arc::new(Mutex::new(netns));
+        let ctl = Arc::new(Mutex::new(ctl));
 
-        tuns[0].configure(config)?;
+        let netlink = match backend {
+            Backend::Netlink => Netlink::new().ok().map(|nl| Arc::new(Mutex::new(nl))),
+            Backend::Ioctl => None,
+        };
 
-        Ok(tuns)
+        let configured_routes = Arc::new(ConfiguredRoutes {
+            index: index.clone(),
+            netns: netns.clone(),
+            routes: Mutex::new(Vec::new()),
+        });
+        let controller = TunController {
+            configured_routes,
+            name,
+            index,
+            netns,
+            layer,
+            multi_queue,
+            ctl,
+            netlink,
+        };
+        let counters = Arc::new(Counters::default());
+
+        Ok(queues
+            .into_iter()
+            .map(|queue| Self {
+                controller: controller.clone(),
+                vnet_hdr_len,
+                queue,
+                counters: counters.clone(),
+            })
+            .collect())
     }
 
-    fn ifreq(&self) -> libc::ifreq {
-        let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
-        let name = self.name.lock().unwrap();
+    /// Send this queue and what is needed to use it over a unix socket, e.g. from a
+    /// privileged helper to a worker without CAP_NET_ADMIN. The descriptor is
+    /// duplicated so the queue stays usable on this side
+    pub fn send_over<S: AsRawFd>(&self, socket: &S) -> Result<()> {
+        let info = QueueInfo {
+            magic: QueueInfo::MAGIC,
+            flags: self.tun_flags(),
+            index: self.controller.ifindex(),
+            vnet_hdr_len: self.vnet_hdr_len as u32,
+            name: self.controller.ifreq().ifr_name,
+        };
+        self.queue
+            .tun
+            .send_over(socket.as_raw_fd(), &info.to_bytes())?;
+        Ok(())
+    }
+
+    /// Rebuild a queue sent with [`Tun::send_over`], the interface is configured
+    /// through netlink if available. Nothing is done which requires CAP_NET_ADMIN.
+    /// The device has to be in the namespace of the receiver, it is configured
+    /// from there, otherwise this fails with ENODEV
+    pub fn recv_from<S: AsRawFd>(socket: &S) -> Result<Self> {
+        let mut data = [0u8; QueueInfo::LEN];
+        let (tun, n) = Fd::recv_from(socket.as_raw_fd(), &mut data)?;
+
+        let info = QueueInfo::from_bytes(&data[..n])
+            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+        let queue = Queue {
+            tun,
+            pi_enabled: info.flags & libc::IFF_NO_PI as c_short == 0,
+        };
+
+        // a device of the same name with another index is not the one of the
+        // queue, the sender sees it in another namespace
+        let ctl = Fd::new(syscall!(socket(libc::AF_INET, libc::SOCK_DGRAM, 0))?)?;
+        let mut ifr = ifreq_for("");
+        ifr.ifr_name = info.name;
+        unsafe { siocgifindex(ctl.as_raw_fd(), &mut ifr) }?;
+        if unsafe { ifr.ifr_ifru.ifru_ifindex } != info.index {
+            return Err(io::Error::from_raw_os_error(libc::ENODEV).into());
+        }
+
+        let mut tuns = Self::from_queues(
+            vec![queue],
+            ctl,
+            &ifr,
+            info.flags,
+            info.vnet_hdr_len as usize,
+            None,
+            Backend::default(),
+        )?;
+
+        Ok(tuns.pop().unwrap())
+    }
+
+    /// Open one more queue of a multi-queue device
+    pub fn open_queue(&self) -> Result<Self> {
+        if !self.controller.multi_queue {
+            return Err(Error::InvalidQueuesNumber);
+        }
+
+        let mut ifr = self.controller.ifreq();
+        ifr.ifr_ifru.ifru_flags = self.tun_flags();
+
+        // TUNSETIFF looks the device up in the namespace of the caller
+        let tun = self.controller.in_netns(|| {
+            let tun_fd = syscall!(open(c"/dev/net/tun".as_ptr(), libc::O_RDWR))?;
+            let tun = Fd::new(tun_fd)?;
+            unsafe { tunsetiff(tun_fd, &mut ifr as *mut libc::ifreq as *mut c_int) }?;
+            Ok(tun)
+        })?;
+
+        Ok(Self {
+            controller: self.controller.clone(),
+            vnet_hdr_len: self.vnet_hdr_len,
+            queue: Queue {
+                tun,
+                pi_enabled: self.has_packet_information(),
+            },
+            counters: self.counters.clone(),
+        })
+    }
+
+    /// Detach the queue from a multi-queue device, or attach it back. The kernel
+    /// does not hand packets to a detached queue and writing to it fails
+    pub fn enable_queue(&self, value: bool) -> Result<()> {
+        let mut ifr = self.controller.ifreq();
+        ifr.ifr_ifru.ifru_flags = if value {
+            libc::IFF_ATTACH_QUEUE
+        } else {
+            libc::IFF_DETACH_QUEUE
+        } as c_short;
+
+        unsafe { tunsetqueue(self.as_raw_fd(), &mut ifr as *mut libc::ifreq as *mut c_int) }?;
+        Ok(())
+    }
+
+    pub fn is_queue_enabled(&self) -> Result<bool> {
+        let mut ifr = self.controller.ifreq();
         unsafe {
-            std::ptr::copy_nonoverlapping(
-                name.as_ptr() as *const _,
-                ifr.ifr_name.as_mut_ptr(),
-                name.len(),
+            tungetiff(
+                self.as_raw_fd(),
+                &mut ifr as *mut libc::ifreq as *mut c_uint,
             )
-        };
+        }?;
+
+        Ok(unsafe { ifr.ifr_ifru.ifru_flags } & libc::IFF_DETACH_QUEUE as c_short == 0)
+    }
 
-        ifr
+    /// The flags the device was created with, as TUNSETIFF expects them
+    fn tun_flags(&self) -> c_short {
+        let mut flags: c_short = self.controller.layer.into();
+        if !self.has_packet_information() {
+            flags |= libc::IFF_NO_PI as c_short;
+        }
+        if self.controller.multi_queue {
+            flags |= libc::IFF_MULTI_QUEUE as c_short;
+        }
+        if self.vnet_hdr_len > 0 {
+            flags |= libc::IFF_VNET_HDR as c_short;
+        }
+        flags
+    }
+
+    /// Remove a device created with [`TunConf::persist`], it must not be in use
+    pub fn destroy_persistent(name: &str) -> Result<()> {
+        if name.len() >= IFNAMSIZ {
+            return Err(Error::NameTooLong);
+        }
+
+        let mut ifr = ifreq_for(name);
+        ifr.ifr_ifru.ifru_flags = tun_flags(name)?;
+
+        let tun = Fd::new(syscall!(open(c"/dev/net/tun".as_ptr(), libc::O_RDWR))?)?;
+        unsafe { tunsetiff(tun.as_raw_fd(), &mut ifr as *mut libc::ifreq as *mut c_int) }?;
+        unsafe { tunsetpersist(tun.as_raw_fd(), 0) }?;
+
+        // the device goes away once `tun` is closed
+        Ok(())
     }
 
     pub fn set_nonblocking(&self) -> io::Result<()> {
@@ -154,16 +631,540 @@ impl Tun {
     pub fn has_packet_information(&self) -> bool {
         self.queue.has_packet_information()
     }
+
+    /// A handle on the interface which outlives this queue, see [`TunController`]
+    pub fn controller(&self) -> TunController {
+        self.controller.clone()
+    }
+
+    // the same as on the controller, the methods of `Interface` are not repeated
+
+    pub fn index(&self) -> Result<i32> {
+        self.controller.index()
+    }
+
+    pub fn move_to_netns(&self, ns: &NetNs) -> Result<()> {
+        self.controller.move_to_netns(ns)
+    }
+
+    pub fn layer(&self) -> Layer {
+        self.controller.layer()
+    }
+
+    pub fn is_multi_queue(&self) -> bool {
+        self.controller.is_multi_queue()
+    }
+
+    pub fn uses_netlink(&self) -> bool {
+        self.controller.uses_netlink()
+    }
+
+    pub fn events(&self) -> Result<Events> {
+        self.controller.events()
+    }
+
+    pub fn set_promiscuous(&self, value: bool) -> Result<()> {
+        self.controller.set_promiscuous(value)
+    }
+
+    pub fn set_noarp(&self, value: bool) -> Result<()> {
+        self.controller.set_noarp(value)
+    }
+
+    pub fn set_multicast(&self, value: bool) -> Result<()> {
+        self.controller.set_multicast(value)
+    }
+
+    pub fn enslave(&self, bridge: &str) -> Result<()> {
+        self.controller.enslave(bridge)
+    }
+
+    pub fn release(&self) -> Result<()> {
+        self.controller.release()
+    }
+
+    pub fn master(&self) -> Result<Option<String>> {
+        self.controller.master()
+    }
+
+    pub fn addresses(&self) -> Result<Vec<Address>> {
+        self.controller.addresses()
+    }
+
+    pub fn add_address(&self, addr: &Address) -> Result<()> {
+        self.controller.add_address(addr)
+    }
+
+    pub fn remove_address(&self, addr: &Address) -> Result<()> {
+        self.controller.remove_address(addr)
+    }
+
+    pub fn routes(&self) -> Result<Vec<Route>> {
+        self.controller.routes()
+    }
+
+    pub fn add_route(&self, route: &Route) -> Result<()> {
+        self.controller.add_route(route)
+    }
+
+    pub fn remove_route(&self, route: &Route) -> Result<()> {
+        self.controller.remove_route(route)
+    }
+
+    pub fn ipv6_address(&self) -> Result<Ipv6Addr> {
+        self.controller.ipv6_address()
+    }
+
+    pub fn ipv6_addresses(&self) -> Result<Vec<(Ipv6Addr, u8)>> {
+        self.controller.ipv6_addresses()
+    }
+
+    pub fn add_ipv6_address(&self, addr: Ipv6Addr, prefix: u8) -> Result<()> {
+        self.controller.add_ipv6_address(addr, prefix)
+    }
+
+    pub fn remove_ipv6_address(&self, addr: Ipv6Addr, prefix: u8) -> Result<()> {
+        self.controller.remove_ipv6_address(addr, prefix)
+    }
+
+    /// Size of the virtio-net header in front of every packet, 0 without IFF_VNET_HDR
+    pub fn vnet_hdr_len(&self) -> usize {
+        self.vnet_hdr_len
+    }
+
+    /// Run `filter` on the packets sent to the device, the ones it rejects are
+    /// dropped by the kernel. The filter applies to every queue of the device,
+    /// the kernel only supports it for TAP devices
+    pub fn attach_filter(&self, filter: &Filter) -> Result<()> {
+        let prog = libc::sock_fprog {
+            len: filter.0.len() as _,
+            filter: filter.0.as_ptr() as *mut _,
+        };
+        unsafe { tunattachfilter(self.as_raw_fd(), &prog) }?;
+        Ok(())
+    }
+
+    pub fn detach_filter(&self) -> Result<()> {
+        let prog: libc::sock_fprog = unsafe { mem::zeroed() };
+        unsafe { tundetachfilter(self.as_raw_fd(), &prog) }?;
+        Ok(())
+    }
+
+    /// Pick the queue of every packet sent to the device with an eBPF program of
+    /// type `BPF_PROG_TYPE_SOCKET_FILTER`, its return value modulo the number of
+    /// queues is the queue index. `None` goes back to the flow hash
+    pub fn set_steering_ebpf(&self, prog: Option<RawFd>) -> Result<()> {
+        let mut fd = prog.unwrap_or(-1);
+        unsafe { tunsetsteeringebpf(self.as_raw_fd(), &mut fd) }?;
+        Ok(())
+    }
+
+    /// Drop the packets sent to the device for which the `BPF_PROG_TYPE_SOCKET_FILTER`
+    /// program returns 0, `None` detaches it. Unlike [`Tun::attach_filter`] this works for TUN too
+    pub fn set_filter_ebpf(&self, prog: Option<RawFd>) -> Result<()> {
+        let mut fd = prog.unwrap_or(-1);
+        unsafe { tunsetfilterebpf(self.as_raw_fd(), &mut fd) }?;
+        Ok(())
+    }
+
+    /// Read a packet along with its virtio-net header, returns the packet length.
+    /// The packet information, if enabled, is skipped
+    pub fn read_vnet(&mut self, buf: &mut [u8]) -> io::Result<(VirtioNetHdr, usize)> {
+        let mut prefix = [0u8; 20];
+        let (prefix, pi) = self.vnet_prefix(&mut prefix)?;
+
+        let res = self
+            .queue
+            .tun
+            .read_vectored(&mut [io::IoSliceMut::new(prefix), io::IoSliceMut::new(buf)])
+            .and_then(|n| {
+                let vnet = VirtioNetHdr::parse(&prefix[pi..])
+                    .filter(|_| n >= prefix.len())
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+                Ok((vnet, n - prefix.len()))
+            });
+        self.counters.read(res.as_ref().map(|(_, n)| n), 0);
+
+        res
+    }
+
+    /// Write a packet behind the given virtio-net header, returns the packet length.
+    /// The packet information, if enabled, is filled in from the packet
+    pub fn write_vnet(&mut self, vnet: &VirtioNetHdr, buf: &[u8]) -> io::Result<usize> {
+        let mut prefix = [0u8; 20];
+        let (prefix, pi) = self.vnet_prefix(&mut prefix)?;
+        if pi > 0 && self.controller.layer == Layer::L3 {
+            let proto = match buf.first().map(|b| b >> 4) {
+                Some(6) => libc::ETH_P_IPV6,
+                _ => libc::ETH_P_IP,
+            };
+            prefix[2..4].copy_from_slice(&(proto as u16).to_be_bytes());
+        }
+        prefix[pi..pi + VirtioNetHdr::LEN].copy_from_slice(&vnet.to_bytes());
+
+        let n = self
+            .queue
+            .tun
+            .write_vectored(&[io::IoSlice::new(prefix), io::IoSlice::new(buf)]);
+        self.counters.written(n.as_ref(), prefix.len());
+
+        Ok(n?.saturating_sub(prefix.len()))
+    }
+
+    /// What the queues of the device opened in this process read and wrote, from
+    /// every [`Tun`] and `AsyncTun` sharing them. Unlike
+    /// [`Interface::statistics`] a packet written is counted as received
+    pub fn counters(&self) -> Statistics {
+        self.counters.snapshot()
+    }
+
+    /// Bytes of packet information and virtio-net header in front of packets
+    fn prefix_len(&self) -> usize {
+        let pi = if self.has_packet_information() { 4 } else { 0 };
+        pi + self.vnet_hdr_len
+    }
+
+    /// The packet information and virtio-net header in front of packets,
+    /// along with the offset of the latter
+    fn vnet_prefix<'a>(&self, buf: &'a mut [u8; 20]) -> io::Result<(&'a mut [u8], usize)> {
+        let pi = if self.has_packet_information() { 4 } else { 0 };
+        if self.vnet_hdr_len < VirtioNetHdr::LEN || pi + self.vnet_hdr_len > buf.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the device has no virtio-net header",
+            ));
+        }
+
+        Ok((&mut buf[..pi + self.vnet_hdr_len], pi))
+    }
 }
 
-impl Interface for Tun {
-    type Queue = Queue;
+impl TunController {
+    pub fn index(&self) -> Result<i32> {
+        Ok(self.ifindex())
+    }
 
-    fn name(&self) -> Result<String> {
+    fn ifindex(&self) -> i32 {
+        self.index.load(Ordering::Relaxed)
+    }
+
+    /// Run `f` in the namespace of the device, for the sockets opened on demand
+    fn in_netns<T, F: FnOnce() -> Result<T>>(&self, f: F) -> Result<T> {
+        let netns = self.netns.lock().unwrap().clone();
+        netns::enter(netns.as_deref(), f)
+    }
+
+    /// Move the device to another namespace, where the queues keep working. Its
+    /// addresses and routes are flushed by the kernel in the process
+    pub fn move_to_netns(&self, ns: &NetNs) -> Result<()> {
+        let target = Arc::new(ns.open()?);
+        self.with_netlink(|nl| {
+            nl.set_link(self.ifindex(), 0, 0, |msg| {
+                msg.attr_u32(libc::IFLA_NET_NS_FD, target.0 as u32);
+            })
+        })?;
+        self.configured_routes.routes.lock().unwrap().clear();
+
+        netns::enter(Some(&target), || {
+            let mut ifr = self.ifreq();
+            let ctl = Fd::new(syscall!(socket(libc::AF_INET, libc::SOCK_DGRAM, 0))?)?;
+            unsafe { siocgifindex(ctl.as_raw_fd(), &mut ifr) }?;
+
+            self.index
+                .store(unsafe { ifr.ifr_ifru.ifru_ifindex }, Ordering::Relaxed);
+            *self.ctl.lock().unwrap() = ctl;
+            if let Some(mut nl) = self.netlink() {
+                *nl = Netlink::new()?;
+            }
+            Ok(())
+        })?;
+
+        *self.netns.lock().unwrap() = Some(target);
+        Ok(())
+    }
+
+    pub fn layer(&self) -> Layer {
+        self.layer
+    }
+
+    pub fn is_multi_queue(&self) -> bool {
+        self.multi_queue
+    }
+
+    fn ifreq(&self) -> libc::ifreq {
+        ifreq_for(&self.name.lock().unwrap())
+    }
+
+    /// Whether the interface is configured through rtnetlink rather than ioctls
+    pub fn uses_netlink(&self) -> bool {
+        self.netlink.is_some()
+    }
+
+    fn netlink(&self) -> Option<MutexGuard<'_, Netlink>> {
+        self.netlink.as_ref().map(|nl| nl.lock().unwrap())
+    }
+
+    /// Run `f` with the shared netlink socket, or a temporary one for the ioctl backend
+    fn with_netlink<T, F: FnOnce(&mut Netlink) -> Result<T>>(&self, f: F) -> Result<T> {
+        match self.netlink() {
+            Some(mut nl) => f(&mut nl),
+            None => f(&mut self.in_netns(Netlink::new)?),
+        }
+    }
+
+    /// Subscribe to the changes of the device made from now on, by anyone
+    pub fn events(&self) -> Result<Events> {
+        let subscription = self.in_netns(|| Netlink::subscribe(events::GROUPS))?;
+        let (link, addresses) =
+            self.with_netlink(|nl| Ok((nl.link(self.ifindex())?, nl.addresses(self.ifindex())?)))?;
+
+        Ok(Events::new(
+            subscription,
+            link,
+            addresses,
+            self.name.clone(),
+        ))
+    }
+
+    /// Receive every frame seen on the link rather than only those sent to the device
+    pub fn set_promiscuous(&self, value: bool) -> Result<()> {
+        self.toggle_flags(InterfaceFlags::PROMISC, value)
+    }
+
+    /// Neither resolve the neighbors nor answer their requests, e.g. for ARP
+    pub fn set_noarp(&self, value: bool) -> Result<()> {
+        self.toggle_flags(InterfaceFlags::NOARP, value)
+    }
+
+    pub fn set_multicast(&self, value: bool) -> Result<()> {
+        self.toggle_flags(InterfaceFlags::MULTICAST, value)
+    }
+
+    fn toggle_flags(&self, flags: InterfaceFlags, value: bool) -> Result<()> {
+        let value = if value {
+            flags
+        } else {
+            InterfaceFlags::empty()
+        };
+        self.update_flags(value, flags.bits())
+    }
+
+    /// Set the flags in the `change` mask to their value in `flags`
+    fn update_flags(&self, flags: InterfaceFlags, change: u32) -> Result<()> {
+        if let Some(mut nl) = self.netlink() {
+            return nl.set_flags(self.ifindex(), flags.bits(), change);
+        }
+
+        let ctl = self.ctl.lock().unwrap();
+        let mut ifr = self.ifreq();
+        unsafe { siocgifflags(ctl.as_raw_fd(), &mut ifr) }?;
+
+        unsafe {
+            let current = ifr.ifr_ifru.ifru_flags as u16 as u32;
+            ifr.ifr_ifru.ifru_flags = ((current & !change) | (flags.bits() & change)) as u16 as i16;
+            siocsifflags(ctl.as_raw_fd(), &ifr)
+        }?;
+
+        Ok(())
+    }
+
+    /// Enslave the device to the bridge `name`, which only takes L2 devices
+    pub fn enslave(&self, bridge: &str) -> Result<()> {
+        if self.layer != Layer::L2 {
+            return Err(Error::UnsupportedLayer);
+        }
+
+        self.with_netlink(|nl| {
+            let master = nl.link_by_name(bridge)?;
+            if master.kind.as_deref() != Some("bridge") {
+                return Err(Error::InvalidName);
+            }
+            nl.set_master(self.ifindex(), master.index)
+        })
+    }
+
+    /// Release the device from its bridge, if any
+    pub fn release(&self) -> Result<()> {
+        self.with_netlink(|nl| nl.set_master(self.ifindex(), 0))
+    }
+
+    /// The name of the bridge the device is enslaved to
+    pub fn master(&self) -> Result<Option<String>> {
+        self.with_netlink(|nl| match nl.link(self.ifindex())?.master {
+            Some(master) => Ok(Some(nl.link(master)?.name)),
+            None => Ok(None),
+        })
+    }
+
+    /// Every IPv4 and IPv6 address of the interface, the primary IPv4 address comes first
+    pub fn addresses(&self) -> Result<Vec<Address>> {
+        self.with_netlink(|nl| nl.addresses(self.ifindex()))
+    }
+
+    pub fn add_address(&self, addr: &Address) -> Result<()> {
+        self.with_netlink(|nl| nl.add_address(self.ifindex(), addr))
+    }
+
+    /// Removing the primary IPv4 address also removes the secondary ones
+    /// unless `net.ipv4.conf.<name>.promote_secondaries` is enabled
+    pub fn remove_address(&self, addr: &Address) -> Result<()> {
+        self.with_netlink(|nl| nl.del_address(self.ifindex(), addr))
+    }
+
+    /// Routes of every table whose output interface is this device
+    pub fn routes(&self) -> Result<Vec<Route>> {
+        self.with_netlink(|nl| nl.routes(self.ifindex()))
+    }
+
+    pub fn add_route(&self, route: &Route) -> Result<()> {
+        self.with_netlink(|nl| nl.add_route(self.ifindex(), route))
+    }
+
+    pub fn remove_route(&self, route: &Route) -> Result<()> {
+        self.with_netlink(|nl| nl.del_route(self.ifindex(), route))
+    }
+
+    fn primary_ipv4(&self, nl: &mut Netlink) -> Result<Address> {
+        nl.addresses(self.ifindex())?
+            .into_iter()
+            .find(|addr| addr.network.is_ipv4())
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EADDRNOTAVAIL).into())
+    }
+
+    /// Netlink can not modify an address in place, NLM_F_REPLACE only updates its
+    /// lifetimes, so the primary IPv4 address is replaced by a modified copy of itself.
+    /// The old one is put back if the new one is refused
+    fn update_ipv4<F: FnOnce(&mut Address) -> Result<()>>(&self, f: F) -> Result<()> {
+        let mut nl = self.netlink().unwrap();
+
+        let before = nl.addresses(self.ifindex())?;
+        let old = self.primary_ipv4(&mut nl)?;
+        let mut new = old.clone();
+        f(&mut new)?;
+        if new == old {
+            return Ok(());
+        }
+
+        nl.del_address(self.ifindex(), &old)?;
+        let res = nl.add_address(self.ifindex(), &new);
+        if res.is_err() {
+            nl.add_address(self.ifindex(), &old)?;
+        }
+
+        // the secondary addresses went away with the primary one,
+        // unless `promote_secondaries` is enabled
+        let after = nl.addresses(self.ifindex())?;
+        for addr in before.iter().filter(|addr| addr.secondary) {
+            if !after.iter().any(|a| a.network == addr.network) {
+                nl.add_address(self.ifindex(), addr)?;
+            }
+        }
+
+        res
+    }
+
+    /// The first IPv6 address assigned to the interface
+    pub fn ipv6_address(&self) -> Result<Ipv6Addr> {
+        self.ipv6_addresses()?
+            .first()
+            .map(|(addr, _)| *addr)
+            .ok_or(Error::InvalidAddress)
+    }
+
+    /// All IPv6 addresses assigned to the interface with their prefix length,
+    /// including the kernel generated link-local one
+    pub fn ipv6_addresses(&self) -> Result<Vec<(Ipv6Addr, u8)>> {
+        if let Some(mut nl) = self.netlink() {
+            return Ok(nl
+                .addresses(self.ifindex())?
+                .into_iter()
+                .filter_map(|addr| match addr.addr() {
+                    IpAddr::V6(local) => Some((local, addr.prefix())),
+                    _ => None,
+                })
+                .collect());
+        }
+
+        let name = self.name.lock().unwrap().clone();
+        let mut addrs = Vec::new();
+
+        let mut ifap: *mut libc::ifaddrs = std::ptr::null_mut();
+        self.in_netns(|| Ok(syscall!(getifaddrs(&mut ifap))?))?;
+
+        let mut cur = ifap;
+        while !cur.is_null() {
+            let ifa = unsafe { &*cur };
+            cur = ifa.ifa_next;
+
+            if ifa.ifa_addr.is_null()
+                || unsafe { (*ifa.ifa_addr).sa_family } != libc::AF_INET6 as libc::sa_family_t
+                || unsafe { CStr::from_ptr(ifa.ifa_name) }.to_bytes() != name.as_bytes()
+            {
+                continue;
+            }
+
+            let addr = unsafe { *(ifa.ifa_addr as *const libc::sockaddr_in6) };
+            let prefix = if ifa.ifa_netmask.is_null() {
+                128
+            } else {
+                let mask = unsafe { *(ifa.ifa_netmask as *const libc::sockaddr_in6) };
+                u128::from_be_bytes(mask.sin6_addr.s6_addr).count_ones() as u8
+            };
+
+            addrs.push((Ipv6Addr::from_in6_addr(addr.sin6_addr), prefix));
+        }
+
+        unsafe { libc::freeifaddrs(ifap) };
+
+        Ok(addrs)
+    }
+
+    pub fn add_ipv6_address(&self, addr: Ipv6Addr, prefix: u8) -> Result<()> {
+        let ifr6 = self.in6_ifreq(addr, prefix)?;
+        if let Some(mut nl) = self.netlink() {
+            return nl.add_address(self.ifindex(), &IpNetwork::new(addr, prefix)?.into());
+        }
+
+        let ctl =
+            self.in_netns(|| Fd::new(syscall!(socket(libc::AF_INET6, libc::SOCK_DGRAM, 0))?))?;
+
+        unsafe { siocsifaddr_in6(ctl.as_raw_fd(), &ifr6) }?;
+
+        Ok(())
+    }
+
+    pub fn remove_ipv6_address(&self, addr: Ipv6Addr, prefix: u8) -> Result<()> {
+        let ifr6 = self.in6_ifreq(addr, prefix)?;
+        if let Some(mut nl) = self.netlink() {
+            return nl.del_address(self.ifindex(), &IpNetwork::new(addr, prefix)?.into());
+        }
+
+        let ctl =
+            self.in_netns(|| Fd::new(syscall!(socket(libc::AF_INET6, libc::SOCK_DGRAM, 0))?))?;
+
+        unsafe { siocdifaddr_in6(ctl.as_raw_fd(), &ifr6) }?;
+
+        Ok(())
+    }
+
+    fn in6_ifreq(&self, addr: Ipv6Addr, prefix: u8) -> Result<libc::in6_ifreq> {
+        if prefix > 128 {
+            return Err(Error::InvalidPrefix(prefix));
+        }
+
+        let mut ifr6: libc::in6_ifreq = unsafe { std::mem::zeroed() };
+        ifr6.ifr6_addr = addr.to_in6_addr();
+        ifr6.ifr6_prefixlen = prefix as u32;
+        ifr6.ifr6_ifindex = self.ifindex();
+
+        Ok(ifr6)
+    }
+
+    pub fn name(&self) -> Result<String> {
         Ok(self.name.lock().unwrap().clone())
     }
 
-    fn set_name(&mut self, new_name: &str) -> Result<()> {
+    pub fn set_name(&self, new_name: &str) -> Result<()> {
         // let new_name = CString::new(new_name)?;
 
         // if new_name.as_bytes_with_nul().len() > IFNAMSIZ {
@@ -173,6 +1174,12 @@ impl Interface for Tun {
             return Err(Error::NameTooLong);
         }
 
+        if let Some(mut nl) = self.netlink() {
+            nl.set_name(self.ifindex(), new_name)?;
+            *self.name.lock().unwrap() = new_name.into();
+            return Ok(());
+        }
+
         let mut ifr = self.ifreq();
         unsafe {
             std::ptr::copy_nonoverlapping(
@@ -189,34 +1196,33 @@ impl Interface for Tun {
         Ok(())
     }
 
-    fn enable(&mut self, value: bool) -> Result<()> {
-        let mut flags = self.flags(None)?;
+    pub fn enable(&self, value: bool) -> Result<()> {
+        self.toggle_flags(InterfaceFlags::UP, value)
+    }
 
-        if value {
-            flags |= libc::IFF_UP as i16 | libc::IFF_RUNNING as i16;
-        } else {
-            flags &= !libc::IFF_UP as i16;
+    pub fn flags(&self) -> Result<InterfaceFlags> {
+        if let Some(mut nl) = self.netlink() {
+            let flags = nl.link(self.ifindex())?.flags;
+            return Ok(InterfaceFlags::from_bits_retain(flags));
         }
 
-        self.flags(Some(flags))?;
+        let mut ifr = self.ifreq();
+
+        unsafe { siocgifflags(self.ctl.lock().unwrap().as_raw_fd(), &mut ifr) }?;
 
-        Ok(())
+        let flags = unsafe { ifr.ifr_ifru.ifru_flags };
+        Ok(InterfaceFlags::from_bits_retain(flags as u16 as u32))
     }
 
-    fn flags(&self, flags: Option<i16>) -> Result<i16> {
-        let mut ifr = self.ifreq();
+    pub fn set_flags(&self, flags: InterfaceFlags) -> Result<()> {
+        self.update_flags(flags, 0xffff)
+    }
 
-        if let Some(flags) = flags {
-            ifr.ifr_ifru.ifru_flags = flags;
-            unsafe { siocsifflags(self.ctl.lock().unwrap().as_raw_fd(), &ifr) }?;
-        } else {
-            unsafe { siocgifflags(self.ctl.lock().unwrap().as_raw_fd(), &mut ifr) }?;
+    pub fn address(&self) -> Result<Ipv4Addr> {
+        if let Some(mut nl) = self.netlink() {
+            return Ok(ipv4(self.primary_ipv4(&mut nl)?.addr()));
         }
 
-        Ok(unsafe { ifr.ifr_ifru.ifru_flags })
-    }
-
-    fn address(&self) -> Result<Ipv4Addr> {
         let mut ifr = self.ifreq();
 
         unsafe { siocgifaddr(self.ctl.lock().unwrap().as_raw_fd(), &mut ifr) }?;
@@ -225,7 +1231,37 @@ impl Interface for Tun {
         Ok(unsafe { ifr.ifr_ifru.ifru_addr }.into_ipv4addr())
     }
 
-    fn set_address(&mut self, addr: Ipv4Addr) -> Result<()> {
+    /// Replace the address of the primary IPv4 address, or add one with the
+    /// netmask of the address class, a host netmask on a point-to-point device
+    pub fn set_address(&self, addr: Ipv4Addr) -> Result<()> {
+        if let Some(mut nl) = self.netlink() {
+            return match self.primary_ipv4(&mut nl) {
+                Ok(_) => {
+                    drop(nl);
+                    self.update_ipv4(|primary| {
+                        primary.network = IpNetwork::new(addr, primary.prefix())?;
+                        Ok(())
+                    })
+                }
+                Err(_) => {
+                    // the same defaults as SIOCSIFADDR, a classful netmask and the
+                    // broadcast address of the network unless the device is point-to-point
+                    let flags = InterfaceFlags::from_bits_retain(nl.link(self.ifindex())?.flags);
+                    let prefix = match classful_prefix(addr) {
+                        Some(_) if flags.contains(InterfaceFlags::POINTOPOINT) => 32,
+                        Some(prefix) => prefix,
+                        None => return Err(Error::InvalidAddress),
+                    };
+                    let network = IpNetwork::new(addr, prefix)?;
+                    let mut address = Address::new(network);
+                    if flags.contains(InterfaceFlags::BROADCAST) && prefix < 31 {
+                        address = address.broadcast(addr | !ipv4(network.netmask()));
+                    }
+                    nl.add_address(self.ifindex(), &address)
+                }
+            };
+        }
+
         let mut ifr = self.ifreq();
         ifr.ifr_ifru.ifru_addr = addr.to_sockaddr();
 
@@ -234,7 +1270,12 @@ impl Interface for Tun {
         Ok(())
     }
 
-    fn destination(&self) -> Result<std::net::Ipv4Addr> {
+    pub fn destination(&self) -> Result<std::net::Ipv4Addr> {
+        if let Some(mut nl) = self.netlink() {
+            let primary = self.primary_ipv4(&mut nl)?;
+            return Ok(ipv4(primary.peer.unwrap_or(primary.addr())));
+        }
+
         let mut ifr = self.ifreq();
 
         unsafe { siocgifdstaddr(self.ctl.lock().unwrap().as_raw_fd(), &mut ifr) }?;
@@ -242,7 +1283,14 @@ impl Interface for Tun {
         Ok(unsafe { ifr.ifr_ifru.ifru_addr }.into_ipv4addr())
     }
 
-    fn set_destination(&mut self, addr: std::net::Ipv4Addr) -> Result<()> {
+    pub fn set_destination(&self, addr: std::net::Ipv4Addr) -> Result<()> {
+        if self.netlink.is_some() {
+            return self.update_ipv4(|primary| {
+                primary.peer = Some(addr.into());
+                Ok(())
+            });
+        }
+
         let mut ifr = self.ifreq();
         ifr.ifr_ifru.ifru_addr = addr.to_sockaddr();
 
@@ -251,7 +1299,12 @@ impl Interface for Tun {
         Ok(())
     }
 
-    fn broadcast(&self) -> Result<std::net::Ipv4Addr> {
+    pub fn broadcast(&self) -> Result<std::net::Ipv4Addr> {
+        if let Some(mut nl) = self.netlink() {
+            let primary = self.primary_ipv4(&mut nl)?;
+            return Ok(primary.broadcast.unwrap_or(Ipv4Addr::UNSPECIFIED));
+        }
+
         let mut ifr = self.ifreq();
 
         unsafe { siocgifbrdaddr(self.ctl.lock().unwrap().as_raw_fd(), &mut ifr) }?;
@@ -259,7 +1312,14 @@ impl Interface for Tun {
         Ok(unsafe { ifr.ifr_ifru.ifru_addr }.into_ipv4addr())
     }
 
-    fn set_broadcast(&mut self, addr: std::net::Ipv4Addr) -> Result<()> {
+    pub fn set_broadcast(&self, addr: std::net::Ipv4Addr) -> Result<()> {
+        if self.netlink.is_some() {
+            return self.update_ipv4(|primary| {
+                primary.broadcast = Some(addr);
+                Ok(())
+            });
+        }
+
         let mut ifr = self.ifreq();
         ifr.ifr_ifru.ifru_addr = addr.to_sockaddr();
 
@@ -268,7 +1328,11 @@ impl Interface for Tun {
         Ok(())
     }
 
-    fn netmask(&self) -> Result<std::net::Ipv4Addr> {
+    pub fn netmask(&self) -> Result<std::net::Ipv4Addr> {
+        if let Some(mut nl) = self.netlink() {
+            return Ok(ipv4(self.primary_ipv4(&mut nl)?.network.netmask()));
+        }
+
         let mut ifr = self.ifreq();
 
         unsafe { siocgifnetmask(self.ctl.lock().unwrap().as_raw_fd(), &mut ifr) }?;
@@ -276,7 +1340,14 @@ impl Interface for Tun {
         Ok(unsafe { ifr.ifr_ifru.ifru_addr }.into_ipv4addr())
     }
 
-    fn set_netmask(&mut self, addr: std::net::Ipv4Addr) -> Result<()> {
+    pub fn set_netmask(&self, addr: std::net::Ipv4Addr) -> Result<()> {
+        if self.netlink.is_some() {
+            return self.update_ipv4(|primary| {
+                primary.network = IpNetwork::from_netmask(primary.addr(), addr)?;
+                Ok(())
+            });
+        }
+
         let mut ifr = self.ifreq();
         ifr.ifr_ifru.ifru_addr = addr.to_sockaddr();
 
@@ -285,7 +1356,58 @@ impl Interface for Tun {
         Ok(())
     }
 
-    fn mtu(&self) -> Result<i32> {
+    /// The primary IPv4 address together with the prefix length of its netmask
+    pub fn network(&self) -> Result<IpNetwork> {
+        IpNetwork::from_netmask(self.address()?, self.netmask()?)
+    }
+
+    pub fn mac_address(&self) -> Result<MacAddr> {
+        if self.layer != Layer::L2 {
+            return Err(Error::UnsupportedLayer);
+        }
+
+        if let Some(mut nl) = self.netlink() {
+            return nl
+                .link(self.ifindex())?
+                .address
+                .ok_or(Error::UnsupportedLayer);
+        }
+
+        let mut ifr = self.ifreq();
+
+        unsafe { siocgifhwaddr(self.ctl.lock().unwrap().as_raw_fd(), &mut ifr) }?;
+
+        let data = unsafe { ifr.ifr_ifru.ifru_hwaddr }.sa_data;
+        Ok(MacAddr(std::array::from_fn(|i| data[i] as u8)))
+    }
+
+    pub fn set_mac_address(&self, addr: MacAddr) -> Result<()> {
+        if self.layer != Layer::L2 {
+            return Err(Error::UnsupportedLayer);
+        }
+
+        if let Some(mut nl) = self.netlink() {
+            return nl.set_address(self.ifindex(), &addr);
+        }
+
+        let mut ifr = self.ifreq();
+        unsafe {
+            ifr.ifr_ifru.ifru_hwaddr.sa_family = libc::ARPHRD_ETHER;
+            for (i, octet) in addr.0.iter().enumerate() {
+                ifr.ifr_ifru.ifru_hwaddr.sa_data[i] = *octet as _;
+            }
+        }
+
+        unsafe { siocsifhwaddr(self.ctl.lock().unwrap().as_raw_fd(), &ifr) }?;
+
+        Ok(())
+    }
+
+    pub fn mtu(&self) -> Result<i32> {
+        if let Some(mut nl) = self.netlink() {
+            return Ok(nl.link(self.ifindex())?.mtu as i32);
+        }
+
         let mut ifr = self.ifreq();
 
         unsafe { siocgifmtu(self.ctl.lock().unwrap().as_raw_fd(), &mut ifr) }?;
@@ -293,7 +1415,11 @@ impl Interface for Tun {
         Ok(unsafe { ifr.ifr_ifru.ifru_mtu })
     }
 
-    fn set_mtu(&mut self, mtu: i32) -> Result<()> {
+    pub fn set_mtu(&self, mtu: i32) -> Result<()> {
+        if let Some(mut nl) = self.netlink() {
+            return nl.set_mtu(self.ifindex(), mtu as u32);
+        }
+
         let mut ifr = self.ifreq();
         ifr.ifr_ifru.ifru_mtu = mtu;
 
@@ -302,6 +1428,124 @@ impl Interface for Tun {
         Ok(())
     }
 
+    pub fn statistics(&self) -> Result<Statistics> {
+        // IFLA_STATS64 is read in the namespace of the device, whatever the backend
+        let link = match self.netlink() {
+            Some(mut nl) => Some(nl.link(self.ifindex())?),
+            None => self
+                .in_netns(Netlink::new)
+                .ok()
+                .map(|mut nl| nl.link(self.ifindex()))
+                .transpose()?,
+        };
+        if let Some(link) = link {
+            return link.stats.ok_or(Error::NotImplemented);
+        }
+
+        // without netlink, only the sysfs of the current namespace is at hand and it may
+        // show another device of the same name
+        if self.netns.lock().unwrap().is_some() {
+            return Err(Error::NotImplemented);
+        }
+        let name = self.name()?;
+        let read = |counter: &str| -> Result<u64> {
+            let path = format!("/sys/class/net/{name}/statistics/{counter}");
+            Ok(std::fs::read_to_string(path)?.trim().parse()?)
+        };
+
+        Ok(Statistics {
+            rx_packets: read("rx_packets")?,
+            tx_packets: read("tx_packets")?,
+            rx_bytes: read("rx_bytes")?,
+            tx_bytes: read("tx_bytes")?,
+            rx_errors: read("rx_errors")?,
+            tx_errors: read("tx_errors")?,
+            rx_dropped: read("rx_dropped")?,
+            tx_dropped: read("tx_dropped")?,
+            multicast: read("multicast")?,
+        })
+    }
+}
+
+impl Interface for Tun {
+    type Queue = Queue;
+
+    fn name(&self) -> Result<String> {
+        self.controller.name()
+    }
+
+    fn set_name(&mut self, new_name: &str) -> Result<()> {
+        self.controller.set_name(new_name)
+    }
+
+    fn enable(&mut self, value: bool) -> Result<()> {
+        self.controller.enable(value)
+    }
+
+    fn flags(&self) -> Result<InterfaceFlags> {
+        self.controller.flags()
+    }
+
+    fn set_flags(&mut self, flags: InterfaceFlags) -> Result<()> {
+        self.controller.set_flags(flags)
+    }
+
+    fn address(&self) -> Result<Ipv4Addr> {
+        self.controller.address()
+    }
+
+    fn set_address(&mut self, addr: Ipv4Addr) -> Result<()> {
+        self.controller.set_address(addr)
+    }
+
+    fn destination(&self) -> Result<std::net::Ipv4Addr> {
+        self.controller.destination()
+    }
+
+    fn set_destination(&mut self, addr: std::net::Ipv4Addr) -> Result<()> {
+        self.controller.set_destination(addr)
+    }
+
+    fn broadcast(&self) -> Result<std::net::Ipv4Addr> {
+        self.controller.broadcast()
+    }
+
+    fn set_broadcast(&mut self, addr: std::net::Ipv4Addr) -> Result<()> {
+        self.controller.set_broadcast(addr)
+    }
+
+    fn netmask(&self) -> Result<std::net::Ipv4Addr> {
+        self.controller.netmask()
+    }
+
+    fn set_netmask(&mut self, addr: std::net::Ipv4Addr) -> Result<()> {
+        self.controller.set_netmask(addr)
+    }
+
+    fn network(&self) -> Result<IpNetwork> {
+        self.controller.network()
+    }
+
+    fn mac_address(&self) -> Result<MacAddr> {
+        self.controller.mac_address()
+    }
+
+    fn set_mac_address(&mut self, addr: MacAddr) -> Result<()> {
+        self.controller.set_mac_address(addr)
+    }
+
+    fn mtu(&self) -> Result<i32> {
+        self.controller.mtu()
+    }
+
+    fn set_mtu(&mut self, mtu: i32) -> Result<()> {
+        self.controller.set_mtu(mtu)
+    }
+
+    fn statistics(&self) -> Result<Statistics> {
+        self.controller.statistics()
+    }
+
     fn queue(&mut self) -> &mut Self::Queue {
         &mut self.queue
     }
@@ -315,17 +1559,23 @@ impl AsRawFd for Tun {
 
 impl Read for Tun {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.queue.tun.read(buf)
+        let n = self.queue.tun.read(buf);
+        self.counters.read(n.as_ref(), self.prefix_len());
+        n
     }
 
     fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
-        self.queue.tun.read_vectored(bufs)
+        let n = self.queue.tun.read_vectored(bufs);
+        self.counters.read(n.as_ref(), self.prefix_len());
+        n
     }
 }
 
 impl Write for Tun {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.queue.tun.write(buf)
+        let n = self.queue.tun.write(buf);
+        self.counters.written(n.as_ref(), self.prefix_len());
+        n
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -333,7 +1583,117 @@ impl Write for Tun {
     }
 
     fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
-        self.queue.tun.write_vectored(bufs)
+        let n = self.queue.tun.write_vectored(bufs);
+        self.counters.written(n.as_ref(), self.prefix_len());
+        n
+    }
+}
+
+fn offload_bits(offloads: &Offloads) -> c_uint {
+    [
+        (offloads.csum, libc::TUN_F_CSUM),
+        (offloads.tso4, libc::TUN_F_TSO4),
+        (offloads.tso6, libc::TUN_F_TSO6),
+        (offloads.tso_ecn, libc::TUN_F_TSO_ECN),
+        (offloads.uso4, libc::TUN_F_USO4),
+        (offloads.uso6, libc::TUN_F_USO6),
+    ]
+    .iter()
+    .filter(|(enabled, _)| *enabled)
+    .fold(0, |bits, (_, bit)| bits | bit)
+}
+
+/// What a receiver needs to know about a queue sent over a unix socket
+#[derive(Clone, Copy)]
+struct QueueInfo {
+    magic: u32,
+    flags: c_short,
+    index: c_int,
+    vnet_hdr_len: u32,
+    name: [libc::c_char; IFNAMSIZ],
+}
+
+impl QueueInfo {
+    const MAGIC: u32 = u32::from_be_bytes(*b"tunq");
+    /// The fields one after the other, in native byte order
+    const LEN: usize = 4 + 2 + 4 + 4 + IFNAMSIZ;
+
+    fn to_bytes(self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[0..4].copy_from_slice(&self.magic.to_ne_bytes());
+        data[4..6].copy_from_slice(&self.flags.to_ne_bytes());
+        data[6..10].copy_from_slice(&self.index.to_ne_bytes());
+        data[10..14].copy_from_slice(&self.vnet_hdr_len.to_ne_bytes());
+        for (byte, c) in data[14..].iter_mut().zip(self.name) {
+            *byte = c as u8;
+        }
+        data
+    }
+
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN || data[0..4] != Self::MAGIC.to_ne_bytes() {
+            return None;
+        }
+
+        let mut name = [0; IFNAMSIZ];
+        for (c, byte) in name.iter_mut().zip(&data[14..]) {
+            *c = *byte as libc::c_char;
+        }
+        Some(Self {
+            magic: Self::MAGIC,
+            flags: c_short::from_ne_bytes(data[4..6].try_into().ok()?),
+            index: c_int::from_ne_bytes(data[6..10].try_into().ok()?),
+            vnet_hdr_len: u32::from_ne_bytes(data[10..14].try_into().ok()?),
+            name,
+        })
+    }
+}
+
+/// The flags an existing device was created with, TUNSETIFF only attaches
+/// to it if they match
+fn tun_flags(name: &str) -> Result<c_short> {
+    let flags = std::fs::read_to_string(format!("/sys/class/net/{name}/tun_flags"))
+        .map_err(|_| io::Error::from_raw_os_error(libc::ENODEV))?;
+    let flags = i32::from_str_radix(flags.trim().trim_start_matches("0x"), 16)?;
+
+    Ok((flags
+        & (libc::IFF_TUN
+            | libc::IFF_TAP
+            | libc::IFF_NO_PI
+            | libc::IFF_MULTI_QUEUE
+            | libc::IFF_VNET_HDR)) as c_short)
+}
+
+fn ifreq_for(name: &str) -> libc::ifreq {
+    let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            name.as_ptr() as *const _,
+            ifr.ifr_name.as_mut_ptr(),
+            name.len().min(IFNAMSIZ - 1),
+        )
+    };
+
+    ifr
+}
+
+/// The prefix length of the class of `addr`, none for multicast addresses
+fn classful_prefix(addr: Ipv4Addr) -> Option<u8> {
+    match addr.octets()[0] {
+        _ if addr.is_broadcast() => Some(0),
+        0 => Some(0),
+        1..=127 => Some(8),
+        128..=191 => Some(16),
+        192..=223 => Some(24),
+        224..=239 => None,
+        _ => Some(32),
+    }
+}
+
+fn ipv4(addr: IpAddr) -> Ipv4Addr {
+    match addr {
+        IpAddr::V4(addr) => addr,
+        IpAddr::V6(addr) => addr.to_ipv4_mapped().unwrap_or(Ipv4Addr::UNSPECIFIED),
     }
 }
 
@@ -6,7 +6,7 @@ use std::{io, mem};
 
 use crate::address::Ipv4AddrExt;
 use crate::configuration::Layer;
-use crate::interface::Interface;
+use crate::interface::{Interface, InterfaceFlags};
 use crate::platform::posix::fd::Fd;
 use crate::{configuration::Configuration, error::Error};
 use crate::{error::*, syscall};
@@ -15,6 +15,7 @@ use libc::{c_char, c_uchar, c_uint, socklen_t};
 use super::sys::*;
 
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TunConf {}
 
 pub struct Queue {
@@ -67,6 +68,10 @@ impl Tun {
             return Err(Error::UnsupportedLayer);
         }
 
+        if !config.ipv6.is_empty() || !config.addresses.is_empty() || !config.routes.is_empty() {
+            return Err(Error::NotImplemented);
+        }
+
         let queue_number = config.queues.unwrap_or(1);
         if queue_number != 1 {
             return Err(Error::InvalidQueuesNumber);
@@ -208,30 +213,22 @@ impl Interface for Tun {
         Err(Error::InvalidName)
     }
 
-    fn enable(&mut self, value: bool) -> Result<()> {
-        let mut flags = self.flags(None)?;
-
-        if value {
-            flags |= libc::IFF_UP as i16 | libc::IFF_RUNNING as i16;
-        } else {
-            flags &= !(libc::IFF_UP as i16);
-        }
+    fn flags(&self) -> Result<InterfaceFlags> {
+        let mut ifr = self.ifreq();
 
-        self.flags(Some(flags))?;
+        unsafe { siocgifflags(self.ctl.as_raw_fd(), &mut ifr) }?;
 
-        Ok(())
+        let flags = unsafe { ifr.ifr_ifru.ifru_flags };
+        Ok(InterfaceFlags::from_bits_retain(flags as u16 as u32))
     }
-    fn flags(&self, flags: Option<i16>) -> Result<i16> {
+
+    fn set_flags(&mut self, flags: InterfaceFlags) -> Result<()> {
         let mut ifr = self.ifreq();
+        ifr.ifr_ifru.ifru_flags = flags.bits() as u16 as i16;
 
-        if let Some(flags) = flags {
-            ifr.ifr_ifru.ifru_flags = flags;
-            unsafe { siocsifflags(self.ctl.as_raw_fd(), &ifr) }?;
-        } else {
-            unsafe { siocgifflags(self.ctl.as_raw_fd(), &mut ifr) }?;
-        }
+        unsafe { siocsifflags(self.ctl.as_raw_fd(), &ifr) }?;
 
-        Ok(unsafe { ifr.ifr_ifru.ifru_flags })
+        Ok(())
     }
 
     fn address(&self) -> Result<Ipv4Addr> {
@@ -1,5 +1,11 @@
 #[cfg(target_os = "linux")]
 mod linux {
+    mod bridge;
+    mod devices;
+    mod events;
+    mod filter;
+    mod netlink;
+    mod netns;
     mod sys;
     pub mod tun;
 }
@@ -23,8 +29,13 @@ pub(crate) mod posix {
 #[cfg(test)]
 mod test {
     use std::net::Ipv4Addr;
+    #[cfg(target_os = "linux")]
+    use std::net::Ipv6Addr;
 
-    use crate::{configuration::Configuration, interface::Interface};
+    use crate::{
+        configuration::Configuration,
+        interface::{Interface, InterfaceFlags},
+    };
 
     #[test]
     #[cfg(target_os = "linux")]
@@ -59,6 +70,1062 @@ mod test {
         assert_eq!(1400, dev.mtu().unwrap());
     }
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn backends_for_linux() {
+        use crate::{configuration::Layer, tun::Backend};
+
+        for (name, backend) in [("tun7", Backend::Netlink), ("tun8", Backend::Ioctl)] {
+            let mut config = Configuration::default();
+
+            let dev = config
+                .name(name)
+                .address("192.168.70.1")
+                .destination("192.168.70.2")
+                .netmask("255.255.255.0")
+                .mtu(1380)
+                .platform(|conf| {
+                    conf.backend(backend);
+                })
+                .up()
+                .build()
+                .unwrap();
+
+            assert_eq!(backend == Backend::Netlink, dev.uses_netlink());
+            assert_eq!(
+                "192.168.70.1".parse::<Ipv4Addr>().unwrap(),
+                dev.address().unwrap()
+            );
+            assert_eq!(
+                "192.168.70.2".parse::<Ipv4Addr>().unwrap(),
+                dev.destination().unwrap()
+            );
+            assert_eq!(
+                "255.255.255.0".parse::<Ipv4Addr>().unwrap(),
+                dev.netmask().unwrap()
+            );
+            assert_eq!(1380, dev.mtu().unwrap());
+            assert!(dev.flags().unwrap().contains(InterfaceFlags::UP));
+        }
+
+        // both give an address without netmask the netmask of its class,
+        // unless the device is point-to-point
+        for (name, backend, layer, address, prefix) in [
+            ("tun38", Backend::Netlink, Layer::L2, "10.38.0.1", 8),
+            ("tun39", Backend::Ioctl, Layer::L2, "172.39.0.1", 16),
+            ("tun40", Backend::Netlink, Layer::L3, "192.168.200.1", 32),
+            ("tun41", Backend::Ioctl, Layer::L3, "192.168.201.1", 32),
+        ] {
+            let address = address.parse::<Ipv4Addr>().unwrap();
+            let netmask = Ipv4Addr::from(u32::MAX.checked_shl(32 - prefix).unwrap_or(0));
+            let broadcast = match layer {
+                Layer::L2 => address | !netmask,
+                Layer::L3 => Ipv4Addr::UNSPECIFIED,
+            };
+
+            let mut config = Configuration::default();
+
+            let dev = config
+                .name(name)
+                .layer(layer)
+                .address(address)
+                .platform(|conf| {
+                    conf.backend(backend);
+                })
+                .build()
+                .unwrap();
+
+            assert_eq!(netmask, dev.netmask().unwrap());
+            assert_eq!(broadcast, dev.broadcast().unwrap());
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn ipv6_for_linux() {
+        let mut config = Configuration::default();
+
+        let dev = config
+            .name("tun6")
+            .address("192.168.60.1")
+            .netmask("255.255.255.0")
+            .ipv6_address("fd00::1", 64)
+            .up()
+            .build()
+            .unwrap();
+
+        let addr = "fd00::1".parse::<Ipv6Addr>().unwrap();
+        assert!(dev.ipv6_addresses().unwrap().contains(&(addr, 64)));
+
+        let extra = "fd00:1::1".parse::<Ipv6Addr>().unwrap();
+        dev.add_ipv6_address(extra, 96).unwrap();
+        assert!(dev.ipv6_addresses().unwrap().contains(&(extra, 96)));
+
+        dev.remove_ipv6_address(extra, 96).unwrap();
+        assert!(!dev.ipv6_addresses().unwrap().contains(&(extra, 96)));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn routes_for_linux() {
+        use crate::route::Route;
+
+        let mut config = Configuration::default();
+
+        let dev = config
+            .name("tun10")
+            .address("192.168.80.1")
+            .netmask("255.255.255.0")
+            .ipv6_address("fd00:80::1", 64)
+            .route(Route::new(Ipv4Addr::new(10, 80, 0, 0), 16).metric(300))
+            .up()
+            .build()
+            .unwrap();
+
+        let configured = Route::new(Ipv4Addr::new(10, 80, 0, 0), 16).metric(300);
+        assert!(dev.routes().unwrap().contains(&configured));
+
+        let v6 = Route::new("fd00:81::".parse::<Ipv6Addr>().unwrap(), 48)
+            .metric(100)
+            .table(100);
+        dev.add_route(&v6).unwrap();
+        assert!(dev.routes().unwrap().contains(&v6));
+
+        dev.remove_route(&v6).unwrap();
+        assert!(!dev.routes().unwrap().contains(&v6));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn addresses_for_linux() {
+        use crate::{network::IpNetwork, tun::Address};
+
+        let mut config = Configuration::default();
+
+        let mut dev = config
+            .name("tun11")
+            .address("192.168.90.1")
+            .netmask("255.255.255.0")
+            .addresses(["192.168.90.2/24", "fd00:90::1/64"])
+            .up()
+            .build()
+            .unwrap();
+
+        let networks: Vec<IpNetwork> = dev
+            .addresses()
+            .unwrap()
+            .iter()
+            .map(|addr| addr.network)
+            .collect();
+        assert_eq!("192.168.90.1/24".parse::<IpNetwork>().unwrap(), networks[0]);
+        assert!(networks.contains(&"192.168.90.2/24".parse().unwrap()));
+        assert!(networks.contains(&"fd00:90::1/64".parse().unwrap()));
+
+        let extra = Address::new("192.168.90.3/24".parse().unwrap()).label("tun11:1");
+        dev.add_address(&extra).unwrap();
+        let added = dev
+            .addresses()
+            .unwrap()
+            .into_iter()
+            .find(|addr| addr.network == extra.network)
+            .unwrap();
+        assert!(added.secondary);
+        assert_eq!(Some("tun11:1"), added.label.as_deref());
+
+        dev.remove_address(&extra).unwrap();
+        assert!(!dev
+            .addresses()
+            .unwrap()
+            .iter()
+            .any(|addr| addr.network == extra.network));
+
+        // the secondary address outlives a change of the primary one
+        dev.set_address(Ipv4Addr::new(192, 168, 90, 4)).unwrap();
+        let networks: Vec<IpNetwork> = dev
+            .addresses()
+            .unwrap()
+            .iter()
+            .map(|addr| addr.network)
+            .collect();
+        assert_eq!("192.168.90.4/24".parse::<IpNetwork>().unwrap(), networks[0]);
+        assert!(networks.contains(&"192.168.90.2/24".parse().unwrap()));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn network_for_linux() {
+        use crate::network::IpNetwork;
+
+        let mut config = Configuration::default();
+
+        let mut dev = config
+            .name("tun12")
+            .network("192.168.100.1/24")
+            .network("fd00:100::1/64")
+            .up()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            "192.168.100.1/24".parse::<IpNetwork>().unwrap(),
+            dev.network().unwrap()
+        );
+        assert!(dev
+            .ipv6_addresses()
+            .unwrap()
+            .contains(&("fd00:100::1".parse().unwrap(), 64)));
+
+        assert!(dev.set_netmask(Ipv4Addr::new(255, 0, 255, 0)).is_err());
+        dev.set_netmask(Ipv4Addr::new(255, 255, 0, 0)).unwrap();
+        assert_eq!(16, dev.network().unwrap().prefix());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn persist_for_linux() {
+        use crate::tun::Tun;
+        use std::path::Path;
+
+        let mut config = Configuration::default();
+
+        let dev = config
+            .name("tun13")
+            .platform(|conf| {
+                conf.persist(true).owner(0).group(0);
+            })
+            .build()
+            .unwrap();
+        drop(dev);
+
+        assert!(Path::new("/sys/class/net/tun13").exists());
+        assert_eq!(
+            "0",
+            std::fs::read_to_string("/sys/class/net/tun13/owner")
+                .unwrap()
+                .trim()
+        );
+
+        Tun::destroy_persistent("tun13").unwrap();
+        assert!(!Path::new("/sys/class/net/tun13").exists());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn attach_for_linux() {
+        use crate::{configuration::Layer, tun::Tun};
+        use std::os::unix::io::AsRawFd;
+
+        let mut config = Configuration::default();
+        let dev = config
+            .name("tun14")
+            .layer(Layer::L2)
+            .platform(|conf| {
+                conf.persist(true).packet_information(true);
+            })
+            .build()
+            .unwrap();
+        drop(dev);
+
+        let mut config = Configuration::default();
+        let dev = config
+            .name("tun14")
+            .platform(|conf| {
+                conf.attach(true);
+            })
+            .build()
+            .unwrap();
+        assert_eq!(Layer::L2, dev.layer());
+        assert!(dev.has_packet_information());
+        assert!(!dev.is_multi_queue());
+
+        let fd = unsafe { libc::dup(dev.as_raw_fd()) };
+        let mut config = Configuration::default();
+        let inherited = config.raw_fd(fd).build().unwrap();
+        assert_eq!("tun14", inherited.name().unwrap());
+        assert_eq!(Layer::L2, inherited.layer());
+        assert!(inherited.has_packet_information());
+        drop(inherited);
+        drop(dev);
+
+        Tun::destroy_persistent("tun14").unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn send_over_for_linux() {
+        use crate::tun::Tun;
+        use std::os::unix::net::UnixStream;
+
+        let mut config = Configuration::default();
+        let dev = config
+            .name("tun15")
+            .platform(|conf| {
+                conf.packet_information(true);
+            })
+            .build()
+            .unwrap();
+
+        let (tx, rx) = UnixStream::pair().unwrap();
+        dev.send_over(&tx).unwrap();
+        let mut received = Tun::recv_from(&rx).unwrap();
+
+        assert_eq!("tun15", received.name().unwrap());
+        assert_eq!(dev.index().unwrap(), received.index().unwrap());
+        assert_eq!(dev.layer(), received.layer());
+        assert!(received.has_packet_information());
+        assert_eq!(0, received.vnet_hdr_len());
+
+        received.set_mtu(1300).unwrap();
+        assert_eq!(1300, dev.mtu().unwrap());
+
+        // the device lives on as long as one of the descriptors
+        drop(dev);
+        assert_eq!("tun15", received.name().unwrap());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn vnet_hdr_for_linux() {
+        use crate::vnet::{Offloads, VirtioNetHdr};
+        use std::net::UdpSocket;
+
+        let mut config = Configuration::default();
+        let mut dev = config
+            .name("tun16")
+            .network("192.168.110.1/24")
+            .platform(|conf| {
+                conf.offloads(Offloads::tso());
+            })
+            .up()
+            .build()
+            .unwrap();
+        assert_eq!(VirtioNetHdr::LEN, dev.vnet_hdr_len());
+
+        let socket = UdpSocket::bind("192.168.110.1:0").unwrap();
+        socket.send_to(b"vnet", "192.168.110.2:9").unwrap();
+
+        let mut buf = [0u8; 2048];
+        let (hdr, n) = loop {
+            let (hdr, n) = dev.read_vnet(&mut buf).unwrap();
+            // skip whatever the kernel sends on its own, e.g. IPv6 router solicitations
+            if buf[0] >> 4 == 4 && buf[9] == libc::IPPROTO_UDP as u8 {
+                break (hdr, n);
+            }
+        };
+        assert_eq!(20 + 8 + 4, n);
+        assert!(!hdr.is_gso());
+
+        assert_eq!(
+            n,
+            dev.write_vnet(&VirtioNetHdr::default(), &buf[..n]).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn tap_for_linux() {
+        use crate::{
+            configuration::Layer,
+            ethernet::{EthernetHeader, MacAddr, ETHERTYPE_ARP},
+            tun::Backend,
+        };
+        use std::{io::Read, net::UdpSocket};
+
+        let mac = MacAddr::new(2, 0, 0, 0, 0x16, 1);
+        for (name, backend, net) in [
+            ("tun22", Backend::Netlink, "192.168.190"),
+            ("tun23", Backend::Ioctl, "192.168.191"),
+        ] {
+            let mut config = Configuration::default();
+            let mut dev = config
+                .name(name)
+                .layer(Layer::L2)
+                .mac_address("02:00:00:00:16:01")
+                .network(format!("{net}.1/24"))
+                .platform(|conf| {
+                    conf.backend(backend);
+                })
+                .up()
+                .build()
+                .unwrap();
+            assert_eq!(mac, dev.mac_address().unwrap());
+
+            // the kernel resolves the destination first
+            let socket = UdpSocket::bind(format!("{net}.1:0")).unwrap();
+            socket.send_to(b"arp", format!("{net}.2:9")).unwrap();
+
+            let mut buf = [0u8; 2048];
+            let header = loop {
+                let n = dev.read(&mut buf).unwrap();
+                let header = EthernetHeader::parse(&buf[..n]).unwrap();
+                if header.ether_type == ETHERTYPE_ARP {
+                    break header;
+                }
+            };
+            assert_eq!(mac, header.source);
+            assert!(header.destination.is_broadcast());
+
+            let other = MacAddr::new(2, 0, 0, 0, 0x16, 2);
+            dev.set_mac_address(other).unwrap();
+            assert_eq!(other, dev.mac_address().unwrap());
+        }
+
+        let mut config = Configuration::default();
+        let mut dev = config.name("tun24").build().unwrap();
+        assert!(dev.mac_address().is_err());
+        assert!(dev.set_mac_address(mac).is_err());
+    }
+
+    #[tokio::test]
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    async fn neighbor_for_linux() {
+        use crate::{configuration::Layer, ethernet::MacAddr, NeighborResponder};
+        use futures::StreamExt;
+        use std::net::{Ipv4Addr, UdpSocket};
+
+        let mut config = Configuration::default();
+        let dev = config
+            .name("tun25")
+            .layer(Layer::L2)
+            .network("192.168.150.1/24")
+            .up()
+            .build_async()
+            .unwrap();
+
+        let gateway = MacAddr::new(2, 0, 0, 0, 0x19, 2);
+        let mut responder = NeighborResponder::new(dev.into_framed());
+        responder.bind(Ipv4Addr::new(192, 168, 150, 2), gateway);
+
+        // sent once the kernel got its answer
+        let socket = UdpSocket::bind("192.168.150.1:0").unwrap();
+        socket.send_to(b"resolved", "192.168.150.2:9").unwrap();
+
+        loop {
+            let pkt = responder.next().await.unwrap().unwrap();
+            if pkt.payload().ends_with(b"resolved") {
+                assert_eq!(gateway, pkt.ethernet().unwrap().destination);
+                break;
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    async fn dhcp_for_linux() {
+        use crate::{configuration::Layer, ethernet::MacAddr, DhcpServer};
+        use futures::StreamExt;
+        use std::{net::UdpSocket, os::fd::AsRawFd, time::Duration};
+
+        let mut config = Configuration::default();
+        let dev = config
+            .name("tun26")
+            .layer(Layer::L2)
+            .network("192.168.160.1/24")
+            .up()
+            .build_async()
+            .unwrap();
+
+        let server_mac = MacAddr::new(2, 0, 0, 0, 0x1a, 1);
+        let mut server = DhcpServer::new(
+            dev.into_framed(),
+            "192.168.160.254/24".parse().unwrap(),
+            server_mac,
+        )
+        .unwrap();
+        server
+            .pool(
+                "192.168.160.100".parse().unwrap(),
+                "192.168.160.120".parse().unwrap(),
+            )
+            .router("192.168.160.254".parse().unwrap())
+            .dns(["192.168.160.53".parse().unwrap()]);
+        let served = tokio::spawn(async move {
+            while let Some(pkt) = server.next().await {
+                pkt.unwrap();
+            }
+        });
+
+        let client = tokio::task::spawn_blocking(|| {
+            let socket = UdpSocket::bind("0.0.0.0:68").unwrap();
+            let name = c"tun26";
+            let res = unsafe {
+                libc::setsockopt(
+                    socket.as_raw_fd(),
+                    libc::SOL_SOCKET,
+                    libc::SO_BINDTODEVICE,
+                    name.as_ptr() as *const _,
+                    name.to_bytes().len() as _,
+                )
+            };
+            assert_eq!(0, res);
+            socket.set_broadcast(true).unwrap();
+            socket
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+
+            // a client with the broadcast flag set, of type `ty`, requesting `addr` if any
+            let message = |ty: u8, addr: Option<[u8; 4]>| {
+                let mut bootp = vec![1, 1, 6, 0, 1, 2, 3, 4, 0, 0, 0x80, 0];
+                bootp.resize(28, 0);
+                bootp.extend_from_slice(&[2, 0, 0, 0, 0x1a, 2]);
+                bootp.resize(236, 0);
+                bootp.extend_from_slice(&[99, 130, 83, 99, 53, 1, ty]);
+                if let Some(addr) = addr {
+                    bootp.extend_from_slice(&[50, 4]);
+                    bootp.extend_from_slice(&addr);
+                }
+                bootp.push(255);
+                bootp
+            };
+
+            let mut buf = [0u8; 1500];
+            let mut answer = |bootp: Vec<u8>| loop {
+                socket.send_to(&bootp, "255.255.255.255:67").unwrap();
+                let n = socket.recv(&mut buf).unwrap();
+                // our own broadcast may come back first
+                if buf[0] == 2 {
+                    return buf[..n].to_vec();
+                }
+            };
+
+            let offer = answer(message(1, None));
+            assert_eq!([1, 2, 3, 4], offer[4..8]);
+            assert_eq!([192, 168, 160, 100], offer[16..20]);
+            assert_eq!([53, 1, 2], offer[240..243]);
+
+            let ack = answer(message(3, Some([192, 168, 160, 100])));
+            assert_eq!([192, 168, 160, 100], ack[16..20]);
+            assert_eq!([53, 1, 5], ack[240..243]);
+            let options = &ack[243..];
+            assert!(options
+                .windows(6)
+                .any(|opt| opt == [3, 4, 192, 168, 160, 254]));
+            assert!(options
+                .windows(6)
+                .any(|opt| opt == [6, 4, 192, 168, 160, 53]));
+        });
+
+        client.await.unwrap();
+        served.abort();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn bridge_for_linux() {
+        use crate::{
+            configuration::Layer,
+            error::Error,
+            tun::{create_bridge, delete_bridge},
+        };
+
+        let _ = delete_bridge("tunbr27");
+        create_bridge("tunbr27").unwrap();
+        assert!(create_bridge("tunbr27").is_err());
+
+        let mut config = Configuration::default();
+        let dev = config
+            .name("tun27")
+            .layer(Layer::L2)
+            .bridge("tunbr27")
+            .up()
+            .build()
+            .unwrap();
+        assert_eq!(Some("tunbr27"), dev.master().unwrap().as_deref());
+
+        dev.release().unwrap();
+        assert_eq!(None, dev.master().unwrap());
+        dev.enslave("tunbr27").unwrap();
+        assert!(matches!(delete_bridge("tun27"), Err(Error::InvalidName)));
+
+        delete_bridge("tunbr27").unwrap();
+        assert_eq!(None, dev.master().unwrap());
+        assert!(dev.enslave("tunbr27").is_err());
+
+        let mut config = Configuration::default();
+        let dev = config.name("tun28").build().unwrap();
+        assert!(matches!(
+            dev.enslave("tunbr27"),
+            Err(Error::UnsupportedLayer)
+        ));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn flags_for_linux() {
+        use crate::{configuration::Layer, tun::Backend};
+
+        for (name, backend) in [("tun29", Backend::Netlink), ("tun30", Backend::Ioctl)] {
+            let mut config = Configuration::default();
+            let mut dev = config
+                .name(name)
+                .layer(Layer::L2)
+                .platform(|conf| {
+                    conf.backend(backend);
+                })
+                .build()
+                .unwrap();
+            assert!(!dev.flags().unwrap().contains(InterfaceFlags::UP));
+
+            dev.set_promiscuous(true).unwrap();
+            dev.set_noarp(true).unwrap();
+            dev.set_multicast(false).unwrap();
+            let flags = dev.flags().unwrap();
+            assert!(flags.contains(InterfaceFlags::PROMISC | InterfaceFlags::NOARP));
+            assert!(!flags.contains(InterfaceFlags::MULTICAST));
+
+            dev.enable(true).unwrap();
+            let flags = dev.flags().unwrap();
+            assert!(flags.contains(InterfaceFlags::UP | InterfaceFlags::PROMISC));
+
+            dev.set_flags((flags - InterfaceFlags::NOARP) | InterfaceFlags::MULTICAST)
+                .unwrap();
+            let flags = dev.flags().unwrap();
+            assert!(flags.contains(InterfaceFlags::UP | InterfaceFlags::MULTICAST));
+            assert!(!flags.intersects(InterfaceFlags::NOARP));
+
+            dev.set_promiscuous(false).unwrap();
+            assert!(!dev.flags().unwrap().contains(InterfaceFlags::PROMISC));
+
+            dev.enable(false).unwrap();
+            assert!(!dev.flags().unwrap().contains(InterfaceFlags::UP));
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn statistics_for_linux() {
+        use crate::tun::Backend;
+        use std::{
+            io::{Read, Write},
+            net::UdpSocket,
+        };
+
+        for (name, backend, net) in [
+            ("tun31", Backend::Netlink, "192.168.142"),
+            ("tun32", Backend::Ioctl, "192.168.143"),
+        ] {
+            let mut config = Configuration::default();
+            let mut dev = config
+                .name(name)
+                .network(format!("{net}.1/24"))
+                .platform(|conf| {
+                    conf.backend(backend).packet_information(true);
+                })
+                .up()
+                .build()
+                .unwrap();
+            let before = dev.statistics().unwrap();
+
+            let socket = UdpSocket::bind(format!("{net}.1:0")).unwrap();
+            socket.send_to(b"stats", format!("{net}.2:9")).unwrap();
+
+            let mut buf = [0u8; 1504];
+            let n = loop {
+                let n = dev.read(&mut buf).unwrap();
+                if buf[4] >> 4 == 4 {
+                    break n;
+                }
+            };
+            dev.write_all(&buf[..n]).unwrap();
+
+            let counters = dev.counters();
+            assert_eq!((1, n as u64 - 4), (counters.rx_packets, counters.rx_bytes));
+            assert!(counters.tx_packets >= 1);
+
+            let stats = dev.statistics().unwrap() - before;
+            assert_eq!(
+                (counters.rx_packets, counters.rx_bytes),
+                (stats.rx_packets, stats.rx_bytes)
+            );
+            assert_eq!(
+                (counters.tx_packets, counters.tx_bytes),
+                (stats.tx_packets, stats.tx_bytes)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn events_for_linux() {
+        use crate::tun::{Address, Event};
+
+        let mut config = Configuration::default();
+        let mut dev = config.name("tun33").up().build().unwrap();
+        let events = dev.events().unwrap();
+
+        let addr = Address::new("192.168.170.1/24".parse().unwrap());
+        dev.set_mtu(1400).unwrap();
+        dev.add_address(&addr).unwrap();
+        dev.remove_address(&addr).unwrap();
+        dev.enable(false).unwrap();
+        dev.set_name("tun33r").unwrap();
+        drop(dev);
+
+        let events: Vec<_> = events
+            .map(|event| match event.unwrap() {
+                // without the label and broadcast filled in by the kernel
+                Event::AddressAdded(addr) => Event::AddressAdded(Address::new(addr.network)),
+                Event::AddressRemoved(addr) => Event::AddressRemoved(Address::new(addr.network)),
+                event => event,
+            })
+            // the link-local address comes and goes with the link
+            .filter(|event| match event {
+                Event::AddressAdded(addr) | Event::AddressRemoved(addr) => addr.addr().is_ipv4(),
+                _ => true,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                Event::Mtu(1400),
+                Event::AddressAdded(addr.clone()),
+                Event::AddressRemoved(addr),
+                Event::Down,
+                Event::Renamed("tun33r".into()),
+                Event::Removed,
+            ],
+            events
+        );
+    }
+
+    #[tokio::test]
+    #[cfg(all(target_os = "linux", feature = "async"))]
+    async fn async_events_for_linux() {
+        use crate::tun::Event;
+        use futures::StreamExt;
+
+        let mut config = Configuration::default();
+        let mut dev = config.name("tun34").up().build_async().unwrap();
+        let events = dev.events().unwrap();
+
+        dev.get_mut().set_mtu(1300).unwrap();
+        dev.get_mut().enable(false).unwrap();
+        drop(dev);
+
+        let mut events: Vec<_> = events.map(Result::unwrap).collect().await;
+        // the link-local address comes and goes with the link
+        events.retain(|event| !matches!(event, Event::AddressAdded(_) | Event::AddressRemoved(_)));
+        assert_eq!(vec![Event::Mtu(1300), Event::Down, Event::Removed], events);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn devices_for_linux() {
+        use crate::{
+            configuration::Layer,
+            error::Error,
+            tun::{device_info, list_devices, Tun},
+        };
+
+        let mut config = Configuration::default();
+        let dev = config
+            .name("tun35")
+            .layer(Layer::L2)
+            .platform(|conf| {
+                conf.persist(true)
+                    .multi_queue(true)
+                    .owner(1000)
+                    .vnet_hdr(true);
+            })
+            .up()
+            .build()
+            .unwrap();
+        drop(dev);
+
+        let mut config = Configuration::default();
+        let dev = config
+            .name("tun36")
+            .platform(|conf| {
+                conf.packet_information(true);
+            })
+            .build()
+            .unwrap();
+
+        let devices = list_devices().unwrap();
+        let info = devices.iter().find(|info| info.name == "tun35").unwrap();
+        assert_eq!(Layer::L2, info.layer);
+        assert!(info.persistent && info.multi_queue && info.vnet_hdr);
+        assert!(!info.packet_information);
+        assert_eq!((Some(1000), None), (info.owner, info.group));
+        assert!(info.flags.contains(InterfaceFlags::UP));
+
+        let info = devices.iter().find(|info| info.name == "tun36").unwrap();
+        assert_eq!(dev.index().unwrap(), info.index);
+        assert_eq!(Layer::L3, info.layer);
+        assert!(info.packet_information);
+        assert!(!info.persistent && !info.multi_queue && !info.vnet_hdr);
+        assert_eq!(info, &device_info("tun36").unwrap());
+
+        assert!(matches!(device_info("lo"), Err(Error::InvalidName)));
+
+        Tun::destroy_persistent("tun35").unwrap();
+        assert!(device_info("tun35").is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn controller_for_linux() {
+        use crate::tun::{Address, TunController};
+        use std::{io::Read, net::UdpSocket, thread};
+
+        fn assert_send_sync<T: Send + Sync + Clone>() {}
+        assert_send_sync::<TunController>();
+
+        let mut config = Configuration::default();
+        let mut dev = config
+            .name("tun37")
+            .network("192.168.180.1/24")
+            .up()
+            .build()
+            .unwrap();
+        let controller = dev.controller();
+
+        // the queue is read elsewhere while the interface is reconfigured
+        let reader = thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            loop {
+                let n = dev.read(&mut buf).unwrap();
+                if buf[..n].ends_with(b"moved") {
+                    break dev;
+                }
+            }
+        });
+
+        let other = controller.clone();
+        thread::spawn(move || other.set_mtu(1280).unwrap())
+            .join()
+            .unwrap();
+        controller
+            .add_address(&Address::new("192.168.181.1/24".parse().unwrap()))
+            .unwrap();
+        assert_eq!(1280, controller.mtu().unwrap());
+
+        let socket = UdpSocket::bind("192.168.181.1:0").unwrap();
+        socket.send_to(b"moved", "192.168.181.2:9").unwrap();
+        let dev = reader.join().unwrap();
+        assert_eq!(1280, dev.mtu().unwrap());
+
+        controller.enable(false).unwrap();
+        assert!(!dev.flags().unwrap().contains(InterfaceFlags::UP));
+
+        // the device is gone with its last queue
+        drop(dev);
+        assert!(controller.mtu().is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn filter_for_linux() {
+        use crate::{configuration::Layer, tun::Filter};
+        use std::{io::Read, net::UdpSocket};
+
+        let mut config = Configuration::default();
+        let mut dev = config
+            .name("tun17")
+            .layer(Layer::L2)
+            .network("192.168.120.1/24")
+            .up()
+            .build()
+            .unwrap();
+
+        let filter = Filter::builder()
+            .protocol(libc::IPPROTO_UDP as u8)
+            .destination("192.168.120.255/32".parse().unwrap())
+            .build();
+        dev.attach_filter(&filter).unwrap();
+
+        let socket = UdpSocket::bind("192.168.120.1:0").unwrap();
+        socket.set_broadcast(true).unwrap();
+        // needs an ARP request first, which is dropped as well
+        socket.send_to(b"dropped", "192.168.120.3:9").unwrap();
+        socket.send_to(b"passed", "192.168.120.255:9").unwrap();
+
+        let mut buf = [0u8; 2048];
+        let n = dev.read(&mut buf).unwrap();
+        assert_eq!([192, 168, 120, 255], buf[14 + 16..14 + 20]);
+        assert_eq!(b"passed", &buf[n - 6..n]);
+
+        dev.set_nonblocking().unwrap();
+        assert!(dev.read(&mut buf).is_err());
+
+        dev.detach_filter().unwrap();
+        dev.cancel_nonblocking().unwrap();
+        socket.send_to(b"again", "192.168.120.255:9").unwrap();
+        // whatever else the kernel sends passes now
+        loop {
+            let n = dev.read(&mut buf).unwrap();
+            if buf[..n].ends_with(b"again") {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn queues_for_linux() {
+        let mut config = Configuration::default();
+        let dev = config
+            .name("tun18")
+            .platform(|conf| {
+                conf.multi_queue(true);
+            })
+            .build()
+            .unwrap();
+        assert!(dev.is_multi_queue());
+
+        let queue = dev.open_queue().unwrap();
+        assert_eq!("tun18", queue.name().unwrap());
+        assert!(queue.is_queue_enabled().unwrap());
+
+        queue.enable_queue(false).unwrap();
+        assert!(!queue.is_queue_enabled().unwrap());
+        assert!(dev.is_queue_enabled().unwrap());
+
+        queue.enable_queue(true).unwrap();
+        assert!(queue.is_queue_enabled().unwrap());
+
+        let mut config = Configuration::default();
+        let single = config.name("tun19").build().unwrap();
+        assert!(single.open_queue().is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn ebpf_for_linux() {
+        use std::{io::Read, net::UdpSocket};
+
+        // BPF_PROG_TYPE_SOCKET_FILTER program returning `value`: mov r0, value; exit
+        fn load(value: i32) -> i32 {
+            let mut insns = [0u64; 2];
+            insns[0] = 0xb7 | (value as u32 as u64) << 32;
+            insns[1] = 0x95;
+            let license = c"GPL";
+
+            let mut attr = [0u64; 16];
+            attr[0] = 1 | (insns.len() as u64) << 32;
+            attr[1] = insns.as_ptr() as u64;
+            attr[2] = license.as_ptr() as u64;
+
+            let fd = unsafe {
+                libc::syscall(
+                    libc::SYS_bpf,
+                    5, // BPF_PROG_LOAD
+                    attr.as_mut_ptr(),
+                    std::mem::size_of_val(&attr),
+                )
+            };
+            assert!(fd >= 0, "{}", std::io::Error::last_os_error());
+            fd as i32
+        }
+
+        let mut config = Configuration::default();
+        let mut queues = config
+            .name("tun20")
+            .network("192.168.130.1/24")
+            .queues(2)
+            .up()
+            .build_multi_queue()
+            .unwrap();
+
+        let steering = load(1);
+        queues[0].set_steering_ebpf(Some(steering)).unwrap();
+
+        let socket = UdpSocket::bind("192.168.130.1:0").unwrap();
+        socket.send_to(b"steered", "192.168.130.2:9").unwrap();
+
+        let mut buf = [0u8; 2048];
+        loop {
+            let n = queues[1].read(&mut buf).unwrap();
+            if buf[..n].ends_with(b"steered") {
+                break;
+            }
+        }
+        // only the steered packet is checked, router solicitations may still be queued
+        queues[0].set_nonblocking().unwrap();
+        while let Ok(n) = queues[0].read(&mut buf) {
+            assert!(!buf[..n].ends_with(b"steered"));
+        }
+
+        queues[0].set_steering_ebpf(None).unwrap();
+
+        let filter = load(0);
+        queues[0].set_filter_ebpf(Some(filter)).unwrap();
+        queues[0].set_filter_ebpf(None).unwrap();
+
+        unsafe {
+            libc::close(steering);
+            libc::close(filter);
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn netns_for_linux() {
+        use crate::{
+            network::IpNetwork,
+            tun::{NetNs, Tun},
+        };
+        use std::{os::fd::AsRawFd, os::unix::net::UnixStream, path::Path};
+
+        // a fresh namespace, kept alive by the descriptor once the thread is gone
+        let ns = std::thread::spawn(|| {
+            assert_eq!(0, unsafe { libc::unshare(libc::CLONE_NEWNET) });
+            std::fs::File::open("/proc/thread-self/ns/net").unwrap()
+        })
+        .join()
+        .unwrap();
+
+        let mut config = Configuration::default();
+        let mut dev = config
+            .name("tun21")
+            .network("192.168.140.1/24")
+            .platform(|conf| {
+                conf.netns(NetNs::Fd(ns.as_raw_fd()));
+            })
+            .up()
+            .build()
+            .unwrap();
+
+        assert!(!Path::new("/sys/class/net/tun21").exists());
+        assert_eq!(
+            "192.168.140.1/24".parse::<IpNetwork>().unwrap(),
+            dev.network().unwrap()
+        );
+        dev.set_mtu(1280).unwrap();
+        assert_eq!(1280, dev.mtu().unwrap());
+
+        // a queue received here can not be configured from here
+        let (tx, rx) = UnixStream::pair().unwrap();
+        dev.send_over(&tx).unwrap();
+        assert!(Tun::recv_from(&rx).is_err());
+
+        // a queue opened from here is one more queue of the device over there
+        let mut config = Configuration::default();
+        let queue = config
+            .name("tun42")
+            .platform(|conf| {
+                conf.netns(NetNs::Fd(ns.as_raw_fd())).multi_queue(true);
+            })
+            .build()
+            .unwrap()
+            .open_queue()
+            .unwrap();
+        assert!(!Path::new("/sys/class/net/tun42").exists());
+        assert_eq!("tun42", queue.name().unwrap());
+        assert!(queue.is_queue_enabled().unwrap());
+
+        dev.move_to_netns(&NetNs::from("/proc/thread-self/ns/net"))
+            .unwrap();
+        assert!(Path::new("/sys/class/net/tun21").exists());
+        assert_eq!(
+            std::fs::read_to_string("/sys/class/net/tun21/ifindex")
+                .unwrap()
+                .trim(),
+            dev.index().unwrap().to_string()
+        );
+        dev.set_address(Ipv4Addr::new(192, 168, 141, 1)).unwrap();
+        assert_eq!(Ipv4Addr::new(192, 168, 141, 1), dev.address().unwrap());
+    }
+
     #[test]
     fn create() {
         let mut config = Configuration::default();
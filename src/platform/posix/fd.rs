@@ -1,6 +1,7 @@
 use crate::{error::*, syscall};
 use std::{
     io::{self, Read, Write},
+    mem,
     os::fd::{AsRawFd, RawFd},
 };
 
@@ -26,6 +27,82 @@ impl Fd {
 
         syscall!(fcntl(self.0, libc::F_SETFL, now)).and(Ok(()))
     }
+
+    /// Send a duplicate of the descriptor along with `data` over a unix socket (SCM_RIGHTS)
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub fn send_over(&self, socket: RawFd, data: &[u8]) -> io::Result<()> {
+        let mut iov = libc::iovec {
+            iov_base: data.as_ptr() as *mut _,
+            iov_len: data.len(),
+        };
+        // u64 keeps the control buffer aligned for cmsghdr
+        let mut control = [0u64; 4];
+
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as _) } as _;
+
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as _) as _;
+            std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, self.0);
+        }
+
+        let n = syscall!(sendmsg(socket, &msg, 0))?;
+        if n as usize != data.len() {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+
+        Ok(())
+    }
+
+    /// Receive a descriptor sent with [`Fd::send_over`], `data` is filled with the payload
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub fn recv_from(socket: RawFd, data: &mut [u8]) -> io::Result<(Fd, usize)> {
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr().cast(),
+            iov_len: data.len(),
+        };
+        let mut control = [0u64; 4];
+
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+
+        #[cfg(target_os = "linux")]
+        let flags = libc::MSG_CMSG_CLOEXEC;
+        #[cfg(not(target_os = "linux"))]
+        let flags = 0;
+
+        let n = syscall!(recvmsg(socket, &mut msg, flags))?;
+
+        let mut fd = None;
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            if !cmsg.is_null()
+                && (*cmsg).cmsg_level == libc::SOL_SOCKET
+                && (*cmsg).cmsg_type == libc::SCM_RIGHTS
+            {
+                fd = Some(Fd(std::ptr::read_unaligned(
+                    libc::CMSG_DATA(cmsg) as *const RawFd
+                )));
+            }
+        }
+
+        match fd {
+            Some(_) if msg.msg_flags & libc::MSG_CTRUNC != 0 => {
+                Err(io::ErrorKind::InvalidData.into())
+            }
+            Some(fd) => Ok((fd, n as _)),
+            None => Err(io::ErrorKind::InvalidData.into()),
+        }
+    }
 }
 
 impl AsRawFd for Fd {
@@ -1,7 +1,7 @@
 #[macro_export]
 macro_rules! syscall {
     ($fn: ident ( $($arg: expr),* ) ) => {{
-        #[allow(unused_unsafe)]
+        #[allow(unused_unsafe, clippy::macro_metavars_in_unsafe)]
         let res = unsafe { libc::$fn($( $arg), *) };
         // if res == -1 {
         if res < 0 {
@@ -0,0 +1,109 @@
+use std::net::IpAddr;
+
+#[cfg(feature = "serde")]
+use crate::{error::Error, network::IpNetwork};
+
+/// A route through the tun device, the output interface is always the device itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "RouteRepr", into = "RouteRepr")
+)]
+pub struct Route {
+    pub destination: IpAddr,
+    pub prefix: u8,
+    pub gateway: Option<IpAddr>,
+    /// Preferred source address for packets sent along the route
+    pub source: Option<IpAddr>,
+    pub metric: Option<u32>,
+    /// Routing table, the main table if not set
+    pub table: Option<u32>,
+}
+
+impl Route {
+    pub fn new<A: Into<IpAddr>>(destination: A, prefix: u8) -> Self {
+        Self {
+            destination: destination.into(),
+            prefix,
+            gateway: None,
+            source: None,
+            metric: None,
+            table: None,
+        }
+    }
+
+    pub fn gateway<A: Into<IpAddr>>(mut self, gateway: A) -> Self {
+        self.gateway = Some(gateway.into());
+        self
+    }
+
+    pub fn source<A: Into<IpAddr>>(mut self, source: A) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn metric(mut self, metric: u32) -> Self {
+        self.metric = Some(metric);
+        self
+    }
+
+    pub fn table(mut self, table: u32) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    pub fn is_ipv4(&self) -> bool {
+        self.destination.is_ipv4()
+    }
+
+    pub fn is_ipv6(&self) -> bool {
+        self.destination.is_ipv6()
+    }
+}
+
+/// The destination is written in CIDR notation in configuration files, an
+/// out of range prefix is written as is and refused when read back
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RouteRepr {
+    destination: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gateway: Option<IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metric: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    table: Option<u32>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RouteRepr> for Route {
+    type Error = Error;
+
+    fn try_from(repr: RouteRepr) -> Result<Self, Error> {
+        let destination: IpNetwork = repr.destination.parse()?;
+        Ok(Self {
+            destination: destination.addr(),
+            prefix: destination.prefix(),
+            gateway: repr.gateway,
+            source: repr.source,
+            metric: repr.metric,
+            table: repr.table,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Route> for RouteRepr {
+    fn from(route: Route) -> Self {
+        Self {
+            destination: format!("{}/{}", route.destination, route.prefix),
+            gateway: route.gateway,
+            source: route.source,
+            metric: route.metric,
+            table: route.table,
+        }
+    }
+}
@@ -0,0 +1,152 @@
+/// The checksum starting at `csum_start` + `csum_offset` still has to be computed
+pub const VIRTIO_NET_HDR_F_NEEDS_CSUM: u8 = 1;
+/// The checksum has already been validated
+pub const VIRTIO_NET_HDR_F_DATA_VALID: u8 = 2;
+
+/// Segmentation the packet is waiting for, the ECN bit is kept apart in [`VirtioNetHdr::ecn`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GsoType {
+    #[default]
+    None,
+    TcpV4,
+    Udp,
+    TcpV6,
+    UdpL4,
+    Other(u8),
+}
+
+impl From<u8> for GsoType {
+    fn from(value: u8) -> Self {
+        match value & !VirtioNetHdr::GSO_ECN {
+            0 => GsoType::None,
+            1 => GsoType::TcpV4,
+            3 => GsoType::Udp,
+            4 => GsoType::TcpV6,
+            5 => GsoType::UdpL4,
+            other => GsoType::Other(other),
+        }
+    }
+}
+
+impl From<GsoType> for u8 {
+    fn from(value: GsoType) -> Self {
+        match value {
+            GsoType::None => 0,
+            GsoType::TcpV4 => 1,
+            GsoType::Udp => 3,
+            GsoType::TcpV6 => 4,
+            GsoType::UdpL4 => 5,
+            GsoType::Other(other) => other,
+        }
+    }
+}
+
+/// `struct virtio_net_hdr` in front of every packet of a device with IFF_VNET_HDR,
+/// the fields are in native byte order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VirtioNetHdr {
+    pub flags: u8,
+    pub gso_type: GsoType,
+    pub ecn: bool,
+    /// Length of the headers to copy in front of every segment
+    pub hdr_len: u16,
+    /// Payload size of every segment
+    pub gso_size: u16,
+    pub csum_start: u16,
+    pub csum_offset: u16,
+}
+
+impl VirtioNetHdr {
+    pub const LEN: usize = 10;
+
+    const GSO_ECN: u8 = 0x80;
+
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::LEN {
+            return None;
+        }
+
+        Some(Self {
+            flags: buf[0],
+            gso_type: buf[1].into(),
+            ecn: buf[1] & Self::GSO_ECN != 0,
+            hdr_len: u16::from_ne_bytes([buf[2], buf[3]]),
+            gso_size: u16::from_ne_bytes([buf[4], buf[5]]),
+            csum_start: u16::from_ne_bytes([buf[6], buf[7]]),
+            csum_offset: u16::from_ne_bytes([buf[8], buf[9]]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut buf = [0u8; Self::LEN];
+        buf[0] = self.flags;
+        buf[1] = u8::from(self.gso_type) | if self.ecn { Self::GSO_ECN } else { 0 };
+        buf[2..4].copy_from_slice(&self.hdr_len.to_ne_bytes());
+        buf[4..6].copy_from_slice(&self.gso_size.to_ne_bytes());
+        buf[6..8].copy_from_slice(&self.csum_start.to_ne_bytes());
+        buf[8..10].copy_from_slice(&self.csum_offset.to_ne_bytes());
+        buf
+    }
+
+    pub fn needs_csum(&self) -> bool {
+        self.flags & VIRTIO_NET_HDR_F_NEEDS_CSUM != 0
+    }
+
+    pub fn is_gso(&self) -> bool {
+        self.gso_type != GsoType::None
+    }
+}
+
+/// Offloads the kernel may leave to us, packets then come with a [`VirtioNetHdr`]
+/// telling what is left to do. Segmentation offloads require `csum`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Offloads {
+    pub csum: bool,
+    pub tso4: bool,
+    pub tso6: bool,
+    pub tso_ecn: bool,
+    /// UDP segmentation, Linux 6.2 and later
+    pub uso4: bool,
+    pub uso6: bool,
+}
+
+impl Offloads {
+    /// Checksum and TCP segmentation offloads, the usual choice for a VPN
+    pub fn tso() -> Self {
+        Self {
+            csum: true,
+            tso4: true,
+            tso6: true,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GsoType, VirtioNetHdr};
+
+    #[test]
+    fn roundtrip() {
+        let hdr = VirtioNetHdr {
+            flags: super::VIRTIO_NET_HDR_F_NEEDS_CSUM,
+            gso_type: GsoType::TcpV4,
+            ecn: true,
+            hdr_len: 40,
+            gso_size: 1400,
+            csum_start: 20,
+            csum_offset: 16,
+        };
+
+        let bytes = hdr.to_bytes();
+        assert_eq!(0x81, bytes[1]);
+        assert_eq!(Some(hdr), VirtioNetHdr::parse(&bytes));
+        assert!(hdr.needs_csum() && hdr.is_gso());
+        assert_eq!(None, VirtioNetHdr::parse(&bytes[..4]));
+    }
+}